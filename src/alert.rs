@@ -0,0 +1,544 @@
+use crate::correlation;
+use crate::temperature::Sensor;
+use crate::types::{diff_clients, ClientChange, ClientInfo, LanUserTable, MacAddr};
+use async_trait::async_trait;
+use futures::future;
+use log::{debug, warn};
+use reqwest::Client;
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::Command;
+#[cfg(feature = "desktop-notifications")]
+use tokio::task;
+use tokio::time;
+
+// How long a notifier may take to deliver an alert, so that a hanging command, webhook or
+// notification daemon doesn't hold up monitoring.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An event worth notifying the user about.
+#[derive(Debug, Clone)]
+pub enum Alert {
+    /// A device that isn't known joined the network.
+    DeviceJoined(ClientInfo),
+    /// A tagged device left the network.
+    DeviceLeft(ClientInfo),
+    /// A temperature sensor exceeded its threshold.
+    Temperature {
+        sensor: Sensor,
+        value: usize,
+        threshold: usize,
+    },
+}
+
+impl Alert {
+    /// Short machine-readable name of the event.
+    pub fn event(&self) -> &'static str {
+        match self {
+            Alert::DeviceJoined(_) => "joined",
+            Alert::DeviceLeft(_) => "left",
+            Alert::Temperature { .. } => "temperature",
+        }
+    }
+
+    /// Device that triggered the alert, if any.
+    pub fn device(&self) -> Option<&ClientInfo> {
+        match self {
+            Alert::DeviceJoined(client) | Alert::DeviceLeft(client) => Some(client),
+            Alert::Temperature { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Alert::DeviceJoined(client) => write!(
+                f,
+                "Unknown device joined: {:?} ({})",
+                client.hostname, client.mac
+            ),
+            Alert::DeviceLeft(client) => write!(
+                f,
+                "Tagged device left: {:?} ({})",
+                client.hostname, client.mac
+            ),
+            Alert::Temperature {
+                sensor,
+                value,
+                threshold,
+            } => write!(
+                f,
+                "The {} is {}°C, above the threshold of {}°C",
+                sensor, value, threshold
+            ),
+        }
+    }
+}
+
+/// Backend to deliver alerts.
+#[async_trait(?Send)]
+pub trait Notifier {
+    async fn notify(&mut self, alert: &Alert) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Runs a shell command for each alert, passing the details as environment variables.
+pub struct CommandNotifier {
+    command: String,
+    timeout: Duration,
+}
+
+impl CommandNotifier {
+    pub fn new(command: String) -> Self {
+        Self {
+            command,
+            timeout: TIMEOUT,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Notifier for CommandNotifier {
+    async fn notify(&mut self, alert: &Alert) -> Result<(), Box<dyn std::error::Error>> {
+        let mut command = Command::new("sh");
+        command
+            .kill_on_drop(true)
+            .arg("-c")
+            .arg(&self.command)
+            .env("CONNECT_BOX_EVENT", alert.event())
+            .env("CONNECT_BOX_MESSAGE", alert.to_string());
+        if let Some(client) = alert.device() {
+            command
+                .env("CONNECT_BOX_MAC", client.mac.to_string())
+                .env("CONNECT_BOX_HOSTNAME", &client.hostname)
+                .env(
+                    "CONNECT_BOX_IPV4",
+                    client.ipv4.map(|ip| ip.to_string()).unwrap_or_default(),
+                )
+                .env(
+                    "CONNECT_BOX_IPV6",
+                    client.ipv6.map(|ip| ip.to_string()).unwrap_or_default(),
+                );
+        }
+
+        let status = time::timeout(self.timeout, command.status())
+            .await
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("Alert command timed out after {:?}.", self.timeout),
+                )
+            })??;
+        if !status.success() {
+            return Err(Box::new(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Alert command failed ({}).", status),
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// POSTs a JSON payload to a webhook URL for each alert.
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_timeout(url, TIMEOUT)
+    }
+
+    fn with_timeout(url: String, timeout: Duration) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = Client::builder().timeout(timeout).build()?;
+        Ok(Self { client, url })
+    }
+}
+
+#[async_trait(?Send)]
+impl Notifier for WebhookNotifier {
+    async fn notify(&mut self, alert: &Alert) -> Result<(), Box<dyn std::error::Error>> {
+        let payload = serde_json::json!({
+            "event": alert.event(),
+            "message": alert.to_string(),
+            "device": alert.device(),
+        });
+        let response = self.client.post(&self.url).json(&payload).send().await?;
+        if !response.status().is_success() {
+            return Err(Box::new(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Invalid webhook response (status = {}).", response.status()),
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Writes alerts to the local syslog daemon.
+pub struct SyslogNotifier {
+    socket: UnixDatagram,
+}
+
+impl SyslogNotifier {
+    const SOCKET_PATH: &'static str = "/dev/log";
+    // Priority for the "user" facility and "warning" severity.
+    const PRIORITY: u8 = 8 + 4;
+
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_path(Path::new(Self::SOCKET_PATH))
+    }
+
+    fn with_path(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        // Alerts are dropped rather than waiting for a busy syslog daemon.
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+}
+
+#[async_trait(?Send)]
+impl Notifier for SyslogNotifier {
+    async fn notify(&mut self, alert: &Alert) -> Result<(), Box<dyn std::error::Error>> {
+        let message = format!(
+            "<{}>connect-box[{}]: {}",
+            Self::PRIORITY,
+            std::process::id(),
+            alert
+        );
+        self.socket.send(message.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Shows alerts as desktop notifications, via D-Bus.
+#[cfg(feature = "desktop-notifications")]
+pub struct DesktopNotifier {
+    timeout: Duration,
+}
+
+#[cfg(feature = "desktop-notifications")]
+impl DesktopNotifier {
+    pub fn new() -> Self {
+        Self { timeout: TIMEOUT }
+    }
+}
+
+#[cfg(feature = "desktop-notifications")]
+#[async_trait(?Send)]
+impl Notifier for DesktopNotifier {
+    async fn notify(&mut self, alert: &Alert) -> Result<(), Box<dyn std::error::Error>> {
+        let body = alert.to_string();
+        // The D-Bus call is blocking, so it runs on another thread, which is left behind if the
+        // notification daemon doesn't answer.
+        let show = task::spawn_blocking(move || {
+            notify_rust::Notification::new()
+                .appname("connect-box")
+                .summary("Connect Box")
+                .body(&body)
+                .show()
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        });
+        time::timeout(self.timeout, show)
+            .await
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("Desktop notification timed out after {:?}.", self.timeout),
+                )
+            })??
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        Ok(())
+    }
+}
+
+/// Detects alert-worthy changes between successive device tables, and dispatches them to the
+/// notifiers.
+pub struct Alerter {
+    known: HashSet<MacAddr>,
+    tagged: HashSet<MacAddr>,
+    notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl Alerter {
+    pub fn new(
+        known: impl IntoIterator<Item = MacAddr>,
+        tagged: impl IntoIterator<Item = MacAddr>,
+        notifiers: Vec<Box<dyn Notifier>>,
+    ) -> Self {
+        Self {
+            known: known.into_iter().collect(),
+            tagged: tagged.into_iter().collect(),
+            notifiers,
+        }
+    }
+
+    /// Records the devices present at startup as known, so that they don't trigger alerts.
+    pub fn start(&mut self, table: &LanUserTable) {
+        for client in &table.wifi.clientinfo {
+            self.known.insert(client.mac);
+        }
+    }
+
+    /// Sends alerts for the changes between two device tables. Devices that join become known,
+    /// so that they only trigger an alert the first time.
+    pub async fn update(
+        &mut self,
+        old: &LanUserTable,
+        new: &LanUserTable,
+        departed: Option<&[ClientInfo]>,
+    ) {
+        let mut changes = diff_clients(&old.wifi.clientinfo, &new.wifi.clientinfo);
+        if let Some(departed) = departed {
+            changes = correlation::correlate(changes, departed);
+        }
+
+        let mut alerts = Vec::new();
+        for change in changes {
+            match change {
+                ClientChange::Added(n) => {
+                    if self.known.insert(n.mac) {
+                        alerts.push(Alert::DeviceJoined(n.clone()));
+                    }
+                }
+                ClientChange::Removed(o) => {
+                    if self.tagged.contains(&o.mac) {
+                        alerts.push(Alert::DeviceLeft(o.clone()));
+                    }
+                }
+                ClientChange::Reconnected { new, .. } => {
+                    self.known.insert(new.mac);
+                }
+                ClientChange::Updated { .. } => (),
+            }
+        }
+
+        for alert in alerts {
+            self.fire(&alert).await;
+        }
+    }
+
+    /// Dispatches an alert to all the notifiers concurrently. Failures are logged but otherwise
+    /// ignored, and the notifiers time out, so that a broken notifier doesn't interrupt
+    /// monitoring.
+    pub async fn fire(&mut self, alert: &Alert) {
+        warn!("{}", alert);
+        debug!("Sending alert to {} notifiers...", self.notifiers.len());
+        let results = future::join_all(
+            self.notifiers
+                .iter_mut()
+                .map(|notifier| notifier.notify(alert)),
+        )
+        .await;
+        for result in results {
+            if let Err(e) = result {
+                warn!("Failed to send alert: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AddressMethod;
+    use futures::channel::mpsc;
+    use futures::stream::StreamExt;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server, StatusCode};
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+
+    fn client(mac: &str, hostname: &str) -> ClientInfo {
+        ClientInfo {
+            mac: mac.parse().unwrap(),
+            hostname: hostname.to_owned(),
+            index: 0,
+            ipv4: Some("192.168.0.10".parse().unwrap()),
+            ipv6: None,
+            interface: "Ethernet 1".to_owned(),
+            interfaceid: 1,
+            method: AddressMethod::Dhcp,
+            lease_time: Duration::from_secs(3600),
+            speed: 0,
+        }
+    }
+
+    fn joined() -> Alert {
+        Alert::DeviceJoined(client("00:11:22:33:44:55", "laptop"))
+    }
+
+    // Local stand-in for a webhook, which answers with the given status after the given delay, and
+    // forwards the bodies it receives.
+    fn webhook(
+        status: StatusCode,
+        delay: Duration,
+    ) -> (SocketAddr, mpsc::UnboundedReceiver<String>) {
+        let (sender, receiver) = mpsc::unbounded();
+        let make_service = make_service_fn(move |_| {
+            let sender = sender.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let sender = sender.clone();
+                    async move {
+                        let body = hyper::body::to_bytes(request.into_body()).await?;
+                        let _ = sender.unbounded_send(String::from_utf8_lossy(&body).into_owned());
+                        time::delay_for(delay).await;
+                        let mut response = Response::new(Body::empty());
+                        *response.status_mut() = status;
+                        Ok::<_, hyper::Error>(response)
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        (addr, receiver)
+    }
+
+    #[tokio::test]
+    async fn command_gets_alert_details() {
+        let dir = std::env::temp_dir().join(format!("connect-box-alert-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("output");
+        let mut notifier = CommandNotifier::new(format!(
+            "echo \"$CONNECT_BOX_EVENT $CONNECT_BOX_MAC $CONNECT_BOX_HOSTNAME $CONNECT_BOX_IPV4\" > {}",
+            output.display()
+        ));
+        notifier.notify(&joined()).await.unwrap();
+        let content = std::fs::read_to_string(&output).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(content, "joined 00:11:22:33:44:55 laptop 192.168.0.10\n");
+    }
+
+    #[tokio::test]
+    async fn command_failure() {
+        let mut notifier = CommandNotifier::new("exit 3".to_owned());
+        assert!(notifier.notify(&joined()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn command_timeout() {
+        let mut notifier = CommandNotifier {
+            command: "sleep 10".to_owned(),
+            timeout: Duration::from_millis(100),
+        };
+        let error = notifier.notify(&joined()).await.unwrap_err();
+        assert!(error.to_string().contains("timed out"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn webhook_gets_json_payload() {
+        let (addr, mut bodies) = webhook(StatusCode::OK, Duration::from_secs(0));
+        let mut notifier = WebhookNotifier::new(format!("http://{}/hook", addr)).unwrap();
+        notifier.notify(&joined()).await.unwrap();
+
+        let payload: serde_json::Value =
+            serde_json::from_str(&bodies.next().await.unwrap()).unwrap();
+        assert_eq!(payload["event"], "joined");
+        assert_eq!(
+            payload["message"],
+            "Unknown device joined: \"laptop\" (00:11:22:33:44:55)"
+        );
+        assert_eq!(payload["device"]["MACAddr"], "00:11:22:33:44:55");
+    }
+
+    #[tokio::test]
+    async fn webhook_error_status() {
+        let (addr, _bodies) = webhook(StatusCode::INTERNAL_SERVER_ERROR, Duration::from_secs(0));
+        let mut notifier = WebhookNotifier::new(format!("http://{}/hook", addr)).unwrap();
+        assert!(notifier.notify(&joined()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn webhook_timeout() {
+        let (addr, _bodies) = webhook(StatusCode::OK, Duration::from_secs(10));
+        let mut notifier = WebhookNotifier::with_timeout(
+            format!("http://{}/hook", addr),
+            Duration::from_millis(100),
+        )
+        .unwrap();
+        assert!(notifier.notify(&joined()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn syslog_message() {
+        let path = std::env::temp_dir().join(format!("connect-box-syslog-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let daemon = UnixDatagram::bind(&path).unwrap();
+        let mut notifier = SyslogNotifier::with_path(&path).unwrap();
+        notifier.notify(&joined()).await.unwrap();
+
+        let mut buffer = [0; 1024];
+        let len = daemon.recv(&mut buffer).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&buffer[..len]),
+            format!(
+                "<12>connect-box[{}]: Unknown device joined: \"laptop\" (00:11:22:33:44:55)",
+                std::process::id()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn syslog_without_daemon() {
+        let path = std::env::temp_dir().join("connect-box-syslog-missing");
+        assert!(SyslogNotifier::with_path(&path).is_err());
+    }
+
+    // Records the events of the alerts it gets.
+    struct Recorder(mpsc::UnboundedSender<&'static str>);
+
+    #[async_trait(?Send)]
+    impl Notifier for Recorder {
+        async fn notify(&mut self, alert: &Alert) -> Result<(), Box<dyn std::error::Error>> {
+            self.0.unbounded_send(alert.event()).unwrap();
+            Ok(())
+        }
+    }
+
+    fn table(clients: Vec<ClientInfo>) -> LanUserTable {
+        LanUserTable {
+            customer: String::new(),
+            total_client: clients.len(),
+            wifi: crate::types::Wifi {
+                clientinfo: clients,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_devices_and_tagged_departures() {
+        let (sender, receiver) = mpsc::unbounded();
+        let laptop = client("00:11:22:33:44:55", "laptop");
+        let phone = client("00:11:22:33:44:66", "phone");
+        let tv = client("00:11:22:33:44:77", "tv");
+        let mut alerter = Alerter::new(
+            vec![tv.mac],
+            vec![laptop.mac],
+            vec![Box::new(Recorder(sender))],
+        );
+
+        let start = table(vec![laptop.clone()]);
+        alerter.start(&start);
+        // The phone is unknown, the TV is known.
+        let joined = table(vec![laptop, phone.clone(), tv.clone()]);
+        alerter.update(&start, &joined, None).await;
+        // The laptop is tagged.
+        let left = table(vec![phone, tv]);
+        alerter.update(&joined, &left, None).await;
+        // The phone only triggers an alert the first time.
+        alerter.update(&left, &joined, None).await;
+
+        drop(alerter);
+        assert_eq!(receiver.collect::<Vec<_>>().await, ["joined", "left"]);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::types::MacAddr;
+use log::warn;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// User-defined names of devices, stored in a text file with one "MAC name" line per device.
+/// Empty lines and lines starting with '#' are ignored.
+pub struct Aliases {
+    path: PathBuf,
+    names: HashMap<MacAddr, String>,
+}
+
+impl Aliases {
+    /// Loads the aliases from a file, which may not exist yet. Invalid lines are skipped.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(Box::new(e)),
+        };
+
+        let mut names = HashMap::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (mac, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match mac.parse() {
+                Ok(mac) if !name.trim().is_empty() => {
+                    names.insert(mac, name.trim().to_owned());
+                }
+                _ => warn!("Skipping invalid alias at line {}: {:?}", i + 1, line),
+            }
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            names,
+        })
+    }
+
+    pub fn get(&self, mac: &MacAddr) -> Option<&str> {
+        self.names.get(mac).map(|name| name.as_str())
+    }
+
+    /// Renames a device, or removes its alias if the name is empty, and saves the aliases file.
+    pub fn set(&mut self, mac: MacAddr, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let name = name.trim();
+        if name.is_empty() {
+            self.names.remove(&mac);
+        } else {
+            self.names.insert(mac, name.to_owned());
+        }
+        self.save()
+    }
+
+    // Rewrites the aliases file, sorted by MAC address.
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let sorted: BTreeMap<&MacAddr, &String> = self.names.iter().collect();
+        let mut content = Vec::new();
+        for (mac, name) in sorted {
+            writeln!(content, "{} {}", mac, name)?;
+        }
+
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, content)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Default location of the aliases file.
+pub fn default_path() -> PathBuf {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    config_dir.join("connect-box").join("aliases")
+}
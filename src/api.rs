@@ -0,0 +1,61 @@
+//! REST API of the web server, through which scripts share the router session of connect-box
+//! rather than logging in themselves, since the router only allows one session at a time.
+
+use crate::commands::{self, ExportFormat};
+use crate::session::{self, SharedRouter};
+use crate::types::{CmState, CmSystemInfo, GlobalSettings, MacAddr};
+use serde::Serialize;
+
+/// An operation of the API.
+pub enum Call {
+    Devices,
+    Status,
+    Channels,
+    Reboot,
+    Block(MacAddr),
+}
+
+#[derive(Serialize)]
+struct Status {
+    settings: GlobalSettings,
+    system: CmSystemInfo,
+    temperature: CmState,
+}
+
+#[derive(Serialize)]
+struct Message {
+    message: String,
+}
+
+/// Makes a call on the shared router, and returns its JSON result.
+pub async fn call(router: &SharedRouter, call: Call) -> Result<String, session::Error> {
+    let json = match call {
+        Call::Devices => {
+            // Same format as the export command.
+            let devices = router.devices().await?;
+            let mut json = Vec::new();
+            commands::export(&devices.wifi.clientinfo, ExportFormat::Json, &mut json)
+                .map_err(|e| e.to_string())?;
+            String::from_utf8(json)?
+        }
+        Call::Status => serde_json::to_string(&Status {
+            settings: router.global_settings().await?,
+            system: router.system_info().await?,
+            temperature: router.temperature().await?,
+        })?,
+        Call::Channels => serde_json::to_string(&router.channels().await?)?,
+        Call::Reboot => {
+            router.reboot().await?;
+            serde_json::to_string(&Message {
+                message: "The router is rebooting".to_owned(),
+            })?
+        }
+        Call::Block(mac) => {
+            router.block_device(mac).await?;
+            serde_json::to_string(&Message {
+                message: format!("Blocked {}", mac),
+            })?
+        }
+    };
+    Ok(json)
+}
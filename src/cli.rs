@@ -1,37 +1,146 @@
-use clap::{App, Arg};
+use crate::columns;
+use crate::theme::Theme;
+use crate::types::MacAddr;
+use clap::{App, AppSettings, Arg, SubCommand};
+use std::net::SocketAddr;
+use std::time::Duration;
 
 pub fn build_cli() -> App<'static, 'static> {
     App::new("ConnectBox")
         .version("0.1")
         .author("G. Endignoux <ggendx@gmail.com>")
         .about("Monitor your ConnectBox router")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("password")
                 .short("p")
                 .long("password")
                 .takes_value(true)
-                .required_unless("demo")
-                .help("Password to connect to the router"),
+                .conflicts_with_all(&["password-file", "password-keyring"])
+                .help(
+                    "Password to connect to the router. Deprecated, as it shows up in the shell \
+                     history and the list of processes: without a password argument, it's read \
+                     from the CONNECT_BOX_PASSWORD environment variable, or else prompted",
+                ),
+        )
+        .arg(
+            Arg::with_name("password-file")
+                .long("password-file")
+                .takes_value(true)
+                .value_name("PATH")
+                .conflicts_with("password-keyring")
+                .help("File containing the password to connect to the router"),
+        )
+        .arg(
+            Arg::with_name("password-keyring")
+                .long("password-keyring")
+                .hidden(!cfg!(feature = "keyring"))
+                .help(
+                    "Read the password from the Secret Service keyring, stored under the \
+                     connect-box service with the host as username",
+                ),
         )
         .arg(
             Arg::with_name("host")
                 .short("h")
                 .long("host")
                 .takes_value(true)
-                .required_unless("demo")
                 .help("IP address of the router"),
         )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .takes_value(true)
+                .value_name("PATH")
+                .help(
+                    "Configuration file with profiles of settings [default: \
+                     $XDG_CONFIG_HOME/connect-box/config.toml]",
+                ),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .takes_value(true)
+                .value_name("NAME")
+                .help(
+                    "Profile of the configuration file to use, instead of the default one. \
+                     Arguments given on the command line override its settings",
+                ),
+        )
         .arg(
             Arg::with_name("tui")
                 .short("t")
                 .long("tui")
-                .help("Launch the ncurses-based TUI"),
+                .help("Launch the terminal UI"),
         )
         .arg(
             Arg::with_name("demo")
                 .long("demo")
                 .help("Use demonstration data"),
         )
+        .arg(
+            Arg::with_name("correlate-macs")
+                .long("correlate-macs")
+                .help("Report devices with randomized MAC addresses as reconnecting"),
+        )
+        .arg(
+            Arg::with_name("alert")
+                .long("alert")
+                .help("Send alerts when unknown devices join or tagged devices leave"),
+        )
+        .arg(
+            Arg::with_name("known")
+                .long("known")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("MAC")
+                .validator(validate_mac)
+                .requires("alert")
+                .help("MAC address of a known device, which doesn't trigger alerts when joining"),
+        )
+        .arg(
+            Arg::with_name("tag")
+                .long("tag")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("MAC")
+                .validator(validate_mac)
+                .requires("alert")
+                .help("MAC address of a tagged device, which triggers an alert when leaving"),
+        )
+        .arg(
+            Arg::with_name("alert-command")
+                .long("alert-command")
+                .takes_value(true)
+                .value_name("COMMAND")
+                .requires("alert")
+                .help(
+                    "Shell command to run for each alert, with details in CONNECT_BOX_* variables",
+                ),
+        )
+        .arg(
+            Arg::with_name("alert-webhook")
+                .long("alert-webhook")
+                .takes_value(true)
+                .value_name("URL")
+                .requires("alert")
+                .help("URL to POST a JSON payload to for each alert"),
+        )
+        .arg(
+            Arg::with_name("alert-syslog")
+                .long("alert-syslog")
+                .requires("alert")
+                .help("Write alerts to syslog"),
+        )
+        .arg(
+            Arg::with_name("alert-desktop")
+                .long("alert-desktop")
+                .requires("alert")
+                .hidden(!cfg!(feature = "desktop-notifications"))
+                .help("Show alerts as desktop notifications"),
+        )
         .arg(
             Arg::with_name("refresh")
                 .long("refresh")
@@ -51,6 +160,74 @@ pub fn build_cli() -> App<'static, 'static> {
                 })
                 .help("Target refresh period of the dashboard, in seconds"),
         )
+        .arg(
+            Arg::with_name("temperature-refresh")
+                .long("temperature-refresh")
+                .takes_value(true)
+                .default_value("60")
+                .validator(|value| {
+                    let i: u64 = value.parse().map_err(|_| {
+                        "The temperature refresh period must be a positive integer".to_owned()
+                    })?;
+                    if i < 1 {
+                        return Err(
+                            "The temperature refresh period must be at least 1 second".to_owned()
+                        );
+                    }
+                    if i > 3600 {
+                        return Err(
+                            "The temperature refresh period must be at most 1 hour".to_owned()
+                        );
+                    }
+                    Ok(())
+                })
+                .help("Refresh period of the temperature, in seconds"),
+        )
+        .arg(
+            Arg::with_name("status-refresh")
+                .long("status-refresh")
+                .takes_value(true)
+                .default_value("60")
+                .validator(|value| {
+                    let i: u64 = value.parse().map_err(|_| {
+                        "The status refresh period must be a positive integer".to_owned()
+                    })?;
+                    if i < 1 {
+                        return Err(
+                            "The status refresh period must be at least 1 second".to_owned()
+                        );
+                    }
+                    if i > 3600 {
+                        return Err("The status refresh period must be at most 1 hour".to_owned());
+                    }
+                    Ok(())
+                })
+                .help("Refresh period of the router status and DOCSIS channels in the TUI, in seconds"),
+        )
+        .arg(
+            Arg::with_name("max-temperature")
+                .long("max-temperature")
+                .takes_value(true)
+                .validator(|value| {
+                    value
+                        .parse::<usize>()
+                        .map(|_| ())
+                        .map_err(|_| "The temperature must be a positive integer".to_owned())
+                })
+                .help("Temperature above which to raise an alert, in °C"),
+        )
+        .arg(
+            Arg::with_name("max-tuner-temperature")
+                .long("max-tuner-temperature")
+                .takes_value(true)
+                .validator(|value| {
+                    value
+                        .parse::<usize>()
+                        .map(|_| ())
+                        .map_err(|_| "The temperature must be a positive integer".to_owned())
+                })
+                .help("Tuner temperature above which to raise an alert, in °C"),
+        )
         .arg(
             Arg::with_name("timeout")
                 .long("timeout")
@@ -89,4 +266,211 @@ pub fn build_cli() -> App<'static, 'static> {
                 })
                 .help("Duration between retries in case of a connection error, in seconds"),
         )
+        .arg(
+            Arg::with_name("record")
+                .long("record")
+                .help("Record the polled data in the history file"),
+        )
+        .arg(
+            Arg::with_name("retention")
+                .long("retention")
+                .takes_value(true)
+                .default_value("7days")
+                .validator(|value| {
+                    parse_duration(&value)
+                        .map(|_| ())
+                        .map_err(|_| "The retention must be a duration, e.g. 7days".to_owned())
+                })
+                .help("Duration for which recorded data is kept in the history file"),
+        )
+        .arg(
+            Arg::with_name("history-file")
+                .long("history-file")
+                .takes_value(true)
+                .global(true)
+                .value_name("PATH")
+                .help(
+                    "Path of the history file [default: $XDG_DATA_HOME/connect-box/history.jsonl]",
+                ),
+        )
+        .arg(
+            Arg::with_name("aliases-file")
+                .long("aliases-file")
+                .takes_value(true)
+                .value_name("PATH")
+                .help(
+                    "Path of the file of device names [default: $XDG_CONFIG_HOME/connect-box/aliases]",
+                ),
+        )
+        .arg(
+            Arg::with_name("linger")
+                .long("linger")
+                .takes_value(true)
+                .default_value("10m")
+                .validator(|value| {
+                    parse_duration(&value)
+                        .map(|_| ())
+                        .map_err(|_| "The linger period must be a duration, e.g. 10m".to_owned())
+                })
+                .help("Duration for which departed devices stay in the TUI"),
+        )
+        .arg(
+            Arg::with_name("joined-highlight")
+                .long("joined-highlight")
+                .takes_value(true)
+                .default_value("5m")
+                .validator(|value| {
+                    parse_duration(&value).map(|_| ()).map_err(|_| {
+                        "The joined highlight period must be a duration, e.g. 5m".to_owned()
+                    })
+                })
+                .help("Duration for which devices that joined are highlighted in the TUI"),
+        )
+        .arg(
+            Arg::with_name("theme")
+                .long("theme")
+                .takes_value(true)
+                .possible_values(&Theme::NAMES)
+                .default_value("default")
+                .help("Color theme of the TUI"),
+        )
+        .arg(
+            Arg::with_name("columns")
+                .long("columns")
+                .takes_value(true)
+                .value_name("LIST")
+                .validator(|value| columns::parse_list(&value).map(|_| ()))
+                .help(
+                    "Comma-separated columns of the device table in the TUI [default: mac,ipv4,ipv6,speed,lease,hostname]",
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("Print the recorded connections of a device")
+                .arg(
+                    Arg::with_name("device")
+                        .long("device")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("MAC")
+                        .validator(validate_mac)
+                        .help("MAC address of the device"),
+                )
+                .arg(
+                    Arg::with_name("since")
+                        .long("since")
+                        .takes_value(true)
+                        .default_value("24h")
+                        .validator(|value| {
+                            parse_duration(&value)
+                                .map(|_| ())
+                                .map_err(|_| "The period must be a duration, e.g. 24h".to_owned())
+                        })
+                        .help("Period to look back"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("block")
+                .about("Deny network access to a device on the router")
+                .arg(
+                    Arg::with_name("device")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("MAC")
+                        .validator(validate_mac)
+                        .help("MAC address of the device"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("reboot").about("Reboot the router"))
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Export the connected devices")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["json", "csv"])
+                        .default_value("json")
+                        .help("Output format"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .help("File to write the devices to, instead of the standard output"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Serve a web dashboard and a JSON API of the router")
+                .arg(
+                    Arg::with_name("listen")
+                        .long("listen")
+                        .takes_value(true)
+                        .value_name("ADDRESS:PORT")
+                        .default_value("127.0.0.1:8080")
+                        .validator(|value| {
+                            value.parse::<SocketAddr>().map(|_| ()).map_err(|_| {
+                                "The listen address must be an IP address and a port, e.g. 127.0.0.1:8080"
+                                    .to_owned()
+                            })
+                        })
+                        .help("Address and port to serve the dashboard on"),
+                )
+                .arg(
+                    Arg::with_name("basic-auth")
+                        .long("basic-auth")
+                        .takes_value(true)
+                        .value_name("USER:PASSWORD")
+                        .validator(|value| {
+                            if value.contains(':') {
+                                Ok(())
+                            } else {
+                                Err("The credentials must be given as USER:PASSWORD".to_owned())
+                            }
+                        })
+                        .help("Require these credentials to see the dashboard"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Manage the configuration file")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("check")
+                        .about("Validate all the profiles of the configuration file"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("report")
+                .about("Print presence and uptime statistics of each device")
+                .arg(
+                    Arg::with_name("period")
+                        .long("period")
+                        .takes_value(true)
+                        .possible_values(&["day", "week"])
+                        .default_value("day")
+                        .help("Calendar period covered by the report, so far: today or this week"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["table", "json", "csv"])
+                        .default_value("table")
+                        .help("Output format"),
+                ),
+        )
+}
+
+/// Parses a human-readable duration, such as "24h" or "7days".
+pub fn parse_duration(value: &str) -> Result<Duration, humantime::DurationError> {
+    humantime::parse_duration(value)
+}
+
+fn validate_mac(value: String) -> Result<(), String> {
+    value.parse::<MacAddr>().map(|_| ()).map_err(|_| {
+        "The MAC address must be 6 hexadecimal octets, e.g. AB:CD:EF:01:23:45".to_owned()
+    })
 }
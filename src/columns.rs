@@ -0,0 +1,150 @@
+use crate::alias;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A column of the device table in the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Mac,
+    Ipv4,
+    Ipv6,
+    Speed,
+    Lease,
+    Hostname,
+    Vendor,
+    Alias,
+    Interface,
+    Method,
+    FirstSeen,
+    Offline,
+}
+
+impl Column {
+    pub const ALL: [Column; 12] = [
+        Column::Mac,
+        Column::Ipv4,
+        Column::Ipv6,
+        Column::Speed,
+        Column::Lease,
+        Column::Hostname,
+        Column::Vendor,
+        Column::Alias,
+        Column::Interface,
+        Column::Method,
+        Column::FirstSeen,
+        Column::Offline,
+    ];
+
+    /// Columns shown when none are configured.
+    pub const DEFAULT: [Column; 6] = [
+        Column::Mac,
+        Column::Ipv4,
+        Column::Ipv6,
+        Column::Speed,
+        Column::Lease,
+        Column::Hostname,
+    ];
+
+    /// Name of the column in the configuration.
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Mac => "mac",
+            Column::Ipv4 => "ipv4",
+            Column::Ipv6 => "ipv6",
+            Column::Speed => "speed",
+            Column::Lease => "lease",
+            Column::Hostname => "hostname",
+            Column::Vendor => "vendor",
+            Column::Alias => "alias",
+            Column::Interface => "interface",
+            Column::Method => "method",
+            Column::FirstSeen => "first-seen",
+            Column::Offline => "offline",
+        }
+    }
+
+    /// Title of the column in the table header.
+    pub fn title(self) -> &'static str {
+        match self {
+            Column::Mac => "MAC",
+            Column::Ipv4 => "IPv4",
+            Column::Ipv6 => "IPv6",
+            Column::Speed => "Speed",
+            Column::Lease => "Lease until",
+            Column::Hostname => "Host",
+            Column::Vendor => "Vendor",
+            Column::Alias => "Alias",
+            Column::Interface => "Interface",
+            Column::Method => "Method",
+            Column::FirstSeen => "First seen",
+            Column::Offline => "Offline",
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Column::ALL
+            .iter()
+            .find(|column| column.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = Column::ALL.iter().map(|column| column.name()).collect();
+                format!(
+                    "Unknown column {:?}, expected one of: {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Parses a list of columns separated by commas or newlines.
+pub fn parse_list(s: &str) -> Result<Vec<Column>, String> {
+    let mut columns: Vec<Column> = Vec::new();
+    for name in s.split([',', '\n']) {
+        let name = name.trim();
+        if name.is_empty() {
+            continue;
+        }
+        let column = name.parse()?;
+        if columns.contains(&column) {
+            return Err(format!("Duplicate column {:?}", name));
+        }
+        columns.push(column);
+    }
+    if columns.is_empty() {
+        return Err("At least one column must be given".to_owned());
+    }
+    Ok(columns)
+}
+
+/// Loads the columns from a configuration file, which may not exist. Lines starting with '#' are
+/// ignored.
+pub fn load(path: &Path) -> Result<Option<Vec<Column>>, Box<dyn std::error::Error>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Box::new(e)),
+    };
+    let content: Vec<&str> = content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect();
+    let columns = parse_list(&content.join("\n")).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            format!("Invalid columns in {}: {}", path.display(), e),
+        )
+    })?;
+    Ok(Some(columns))
+}
+
+/// Default location of the columns configuration file, next to the aliases file.
+pub fn default_path() -> PathBuf {
+    alias::default_path().with_file_name("columns")
+}
@@ -0,0 +1,186 @@
+use crate::report::csv_escape;
+use crate::router::Router;
+use crate::types::{ClientInfo, MacAddr};
+use serde::Serialize;
+use std::io::Write;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Operations on the router, run either as CLI subcommands or from the command palette of the TUI.
+pub enum Command {
+    Block(MacAddr),
+    Reboot,
+    /// Exports the devices to a file, or to the standard output if no path is given.
+    Export(ExportFormat, Option<PathBuf>),
+    /// Changes the refresh period of the devices. Only available in the TUI.
+    Refresh(Duration),
+}
+
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(format!(
+                "Unknown export format {:?}, expected json or csv",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    /// Parses a command of the TUI palette, e.g. "block AB:CD:EF:01:23:45" or "refresh 10".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let name = words.next().ok_or_else(|| "Empty command".to_owned())?;
+        let args: Vec<&str> = words.collect();
+        let command = match (name, args.as_slice()) {
+            ("block", [mac]) => Command::Block(
+                mac.parse()
+                    .map_err(|_| format!("Invalid MAC address {:?}", mac))?,
+            ),
+            ("reboot", []) => Command::Reboot,
+            ("export", [format]) => Command::Export(format.parse()?, None),
+            ("export", [format, path]) => Command::Export(format.parse()?, Some(path.into())),
+            ("refresh", [seconds]) => match seconds.parse::<u64>() {
+                Ok(seconds) if (1..=3600).contains(&seconds) => {
+                    Command::Refresh(Duration::from_secs(seconds))
+                }
+                _ => return Err("The refresh period must be between 1 and 3600 seconds".to_owned()),
+            },
+            ("block", _) => return Err("Usage: block <MAC>".to_owned()),
+            ("reboot", _) => return Err("Usage: reboot".to_owned()),
+            ("export", _) => return Err("Usage: export json|csv [PATH]".to_owned()),
+            ("refresh", _) => return Err("Usage: refresh <SECONDS>".to_owned()),
+            _ => return Err(format!("Unknown command {:?}", name)),
+        };
+        Ok(command)
+    }
+}
+
+/// Runs a command, and returns a summary of its outcome.
+pub async fn run<R: Router>(
+    router: &mut R,
+    command: &Command,
+) -> Result<String, Box<dyn std::error::Error>> {
+    match command {
+        Command::Block(mac) => {
+            router.block_device(mac).await?;
+            Ok(format!("Blocked {}", mac))
+        }
+        Command::Reboot => {
+            router.reboot().await?;
+            Ok("The router is rebooting".to_owned())
+        }
+        Command::Export(format, path) => {
+            let devices = router.devices().await?;
+            let clients = &devices.wifi.clientinfo;
+            match path {
+                Some(path) => {
+                    let mut content = Vec::new();
+                    export(clients, *format, &mut content)?;
+                    std::fs::write(path, content)?;
+                    Ok(format!(
+                        "Exported {} devices to {}",
+                        clients.len(),
+                        path.display()
+                    ))
+                }
+                None => {
+                    let stdout = std::io::stdout();
+                    export(clients, *format, &mut stdout.lock())?;
+                    Ok(format!("Exported {} devices", clients.len()))
+                }
+            }
+        }
+        Command::Refresh(_) => Err("The refresh period can only be changed in the TUI".into()),
+    }
+}
+
+// A device as exported.
+#[derive(Serialize)]
+struct ExportedDevice<'a> {
+    mac: MacAddr,
+    hostname: &'a str,
+    ipv4: Option<Ipv4Addr>,
+    ipv6: Option<Ipv6Addr>,
+    interface: String,
+    interface_kind: String,
+    method: String,
+    lease_seconds: u64,
+    speed: usize,
+}
+
+/// Writes the devices as JSON or CSV.
+pub fn export(
+    clients: &[ClientInfo],
+    format: ExportFormat,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let devices: Vec<ExportedDevice> = clients
+        .iter()
+        .map(|client| ExportedDevice {
+            mac: client.mac,
+            hostname: &client.hostname,
+            ipv4: client.ipv4,
+            ipv6: client.ipv6,
+            interface: client.interface.clone(),
+            interface_kind: client.interface_kind().to_string(),
+            method: client.method.to_string(),
+            lease_seconds: client.lease_time.as_secs(),
+            speed: client.speed,
+        })
+        .collect();
+
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, &devices)?;
+            writeln!(out)?;
+        }
+        ExportFormat::Csv => {
+            writeln!(
+                out,
+                "mac,hostname,ipv4,ipv6,interface,interface_kind,method,lease_seconds,speed"
+            )?;
+            let address = |ip: Option<String>| ip.unwrap_or_default();
+            for device in &devices {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{}",
+                    device.mac,
+                    csv_escape(device.hostname),
+                    address(device.ipv4.map(|ip| ip.to_string())),
+                    address(device.ipv6.map(|ip| ip.to_string())),
+                    csv_escape(&device.interface),
+                    device.interface_kind,
+                    device.method,
+                    device.lease_seconds,
+                    device.speed
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
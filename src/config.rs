@@ -0,0 +1,356 @@
+//! Configuration file, with named profiles of settings. The settings of a profile are the long
+//! names of command-line arguments, so they are validated like the command line, and arguments
+//! given on the command line take precedence over them. Settings of a subcommand are in a table
+//! named after it, and only apply when it is run.
+
+use crate::alias;
+use crate::cli;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Config {
+    // Profile used when none is selected with --profile.
+    default_profile: Option<String>,
+    #[serde(default)]
+    profile: BTreeMap<String, Profile>,
+}
+
+type Profile = BTreeMap<String, Value>;
+
+// Arguments that select the configuration, which can't be part of it.
+const COMMAND_LINE_ONLY: &[&str] = &["config", "profile", "help", "version"];
+
+// Arguments that conflict with each other, so that giving one of them on the command line overrides
+// all of them in the profile.
+const EXCLUSIVE: &[&[&str]] = &[&["password", "password-file", "password-keyring"]];
+
+/// Default location of the configuration file, next to the aliases file.
+pub fn default_path() -> PathBuf {
+    alias::default_path().with_file_name("config.toml")
+}
+
+fn load(path: &Path) -> Result<Option<Config>, Box<dyn std::error::Error>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Box::new(e)),
+    };
+    let config = toml::from_str(&content).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            format!("Invalid configuration {}: {}", path.display(), e),
+        )
+    })?;
+    Ok(Some(config))
+}
+
+/// Command-line arguments of a profile.
+#[derive(Debug, Default)]
+pub struct ProfileArgs {
+    global: Vec<OsString>,
+    subcommand: Vec<OsString>,
+}
+
+impl ProfileArgs {
+    pub fn is_empty(&self) -> bool {
+        self.global.is_empty() && self.subcommand.is_empty()
+    }
+
+    /// Adds the arguments to a command line: those of connect-box at the start, and those of the
+    /// subcommand at the end, after its own arguments.
+    pub fn insert_into(self, args: &mut Vec<OsString>) {
+        args.splice(1..1, self.global);
+        args.extend(self.subcommand);
+    }
+}
+
+/// Returns the command-line arguments of the selected profile, or of the default one if none is
+/// selected, leaving out those given on the command line.
+pub fn profile_args(
+    path: &Path,
+    selected: Option<&str>,
+    matches: &clap::ArgMatches,
+) -> Result<ProfileArgs, Box<dyn std::error::Error>> {
+    match load(path)? {
+        Some(config) => config_profile_args(&config, path, selected, matches),
+        None if selected.is_some() => Err(Box::new(io::Error::new(
+            io::ErrorKind::Other,
+            format!("No configuration file at {}", path.display()),
+        ))),
+        None => Ok(ProfileArgs::default()),
+    }
+}
+
+fn config_profile_args(
+    config: &Config,
+    path: &Path,
+    selected: Option<&str>,
+    matches: &clap::ArgMatches,
+) -> Result<ProfileArgs, Box<dyn std::error::Error>> {
+    let name = match selected.or(config.default_profile.as_deref()) {
+        Some(name) => name,
+        None => return Ok(ProfileArgs::default()),
+    };
+    let profile = config.profile.get(name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "No profile {:?} in the configuration {}",
+                name,
+                path.display()
+            ),
+        )
+    })?;
+    let invalid = |e| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "Invalid profile {:?} in the configuration {}: {}",
+                name,
+                path.display(),
+                e
+            ),
+        )
+    };
+    validate(profile).map_err(invalid)?;
+
+    let mut args = ProfileArgs::default();
+    for (key, value) in settings(profile) {
+        if !given(key, matches) {
+            push_args(key, value, &mut args.global).map_err(invalid)?;
+        }
+    }
+    if let (subcommand, Some(matches)) = matches.subcommand() {
+        if let Some(Value::Table(subcommand_settings)) = profile.get(subcommand) {
+            for (key, value) in subcommand_settings {
+                if !given(key, matches) {
+                    push_args(key, value, &mut args.subcommand).map_err(invalid)?;
+                }
+            }
+        }
+    }
+    Ok(args)
+}
+
+// Whether an argument, or one that it conflicts with, is given on the command line.
+fn given(key: &str, matches: &clap::ArgMatches) -> bool {
+    EXCLUSIVE
+        .iter()
+        .find(|keys| keys.contains(&key))
+        .map_or(&[key][..], |keys| keys)
+        .iter()
+        .any(|key| matches.occurrences_of(key) > 0)
+}
+
+// Settings of connect-box in a profile, without the tables of the subcommands.
+fn settings(profile: &Profile) -> impl Iterator<Item = (&String, &Value)> {
+    profile.iter().filter(|(_, value)| !value.is_table())
+}
+
+// Settings of the subcommands in a profile.
+fn subcommand_settings(profile: &Profile) -> impl Iterator<Item = (&String, &toml::value::Table)> {
+    profile
+        .iter()
+        .filter_map(|(key, value)| value.as_table().map(|table| (key, table)))
+}
+
+/// Validates all the profiles of the configuration, and returns how many there are.
+pub fn check(path: &Path) -> Result<usize, Vec<String>> {
+    let config = match load(path) {
+        Ok(Some(config)) => config,
+        Ok(None) => return Err(vec![format!("No configuration file at {}", path.display())]),
+        Err(e) => return Err(vec![e.to_string()]),
+    };
+    let mut errors = Vec::new();
+    if let Some(name) = &config.default_profile {
+        if !config.profile.contains_key(name) {
+            errors.push(format!("The default profile {:?} doesn't exist", name));
+        }
+    }
+    for (name, profile) in &config.profile {
+        if let Err(e) = validate(profile) {
+            errors.push(format!("Profile {:?}: {}", name, e));
+        }
+    }
+    if errors.is_empty() {
+        Ok(config.profile.len())
+    } else {
+        Err(errors)
+    }
+}
+
+// Checks the settings of a profile like command-line arguments.
+fn validate(profile: &Profile) -> Result<(), String> {
+    let mut args = vec![OsString::from("connect-box")];
+    for (key, value) in settings(profile) {
+        push_args(key, value, &mut args)?;
+    }
+    parse(args).map_err(|(_, message)| message)?;
+
+    for (subcommand, settings) in subcommand_settings(profile) {
+        if COMMAND_LINE_ONLY.contains(&subcommand.as_str()) {
+            return Err(format!(
+                "The {:?} subcommand can't have settings",
+                subcommand
+            ));
+        }
+        let mut args = vec![OsString::from("connect-box"), OsString::from(subcommand)];
+        if let Err((clap::ErrorKind::UnknownArgument, _)) = parse(args.clone()) {
+            return Err(format!("Unknown subcommand {:?}", subcommand));
+        }
+        for (key, value) in settings {
+            push_args(key, value, &mut args)?;
+        }
+        // The positional arguments of the subcommand, like the MAC address of block, are only
+        // given on the command line.
+        match parse(args) {
+            Ok(()) | Err((clap::ErrorKind::MissingRequiredArgument, _)) => (),
+            Err((_, message)) => return Err(format!("{}: {}", subcommand, message)),
+        }
+    }
+    Ok(())
+}
+
+// Parses command-line arguments, and returns the kind and description of the error if they are
+// invalid.
+fn parse(args: Vec<OsString>) -> Result<(), (clap::ErrorKind, String)> {
+    cli::build_cli()
+        .get_matches_from_safe(args)
+        .map(|_| ())
+        .map_err(|e| {
+            // Only keep the description of the error, without the usage.
+            let message = e.message.lines().next().unwrap_or_default();
+            (e.kind, message.trim_start_matches("error: ").to_owned())
+        })
+}
+
+// Converts a setting into command-line arguments: flags are set with true, and arguments given
+// multiple times with an array.
+fn push_args(key: &str, value: &Value, args: &mut Vec<OsString>) -> Result<(), String> {
+    if COMMAND_LINE_ONLY.contains(&key) {
+        return Err(format!(
+            "The {:?} setting can only be given on the command line",
+            key
+        ));
+    }
+    match value {
+        Value::Boolean(true) => args.push(format!("--{}", key).into()),
+        Value::Boolean(false) => (),
+        Value::String(s) => args.push(format!("--{}={}", key, s).into()),
+        Value::Integer(i) => args.push(format!("--{}={}", key, i).into()),
+        Value::Float(f) => args.push(format!("--{}={}", key, f).into()),
+        Value::Array(values) => {
+            for value in values {
+                push_args(key, value, args)?;
+            }
+        }
+        Value::Datetime(_) | Value::Table(_) => {
+            return Err(format!("Unsupported value for the {:?} setting", key))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        default-profile = "home"
+
+        [profile.home]
+        host = "192.168.0.1"
+        password-file = "/etc/connect-box/password"
+
+        [profile.home.serve]
+        listen = "127.0.0.1:8080"
+        basic-auth = "admin:secret"
+    "#;
+
+    // Parses a command line with the settings of the default profile.
+    fn command_line(config: &str, args: &[&str]) -> Vec<OsString> {
+        let config: Config = toml::from_str(config).unwrap();
+        let mut args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        let matches = cli::build_cli().get_matches_from(&args);
+        config_profile_args(&config, Path::new("config.toml"), None, &matches)
+            .unwrap()
+            .insert_into(&mut args);
+        args
+    }
+
+    fn validate_str(profile: &str) -> Result<(), String> {
+        validate(&toml::from_str(profile).unwrap())
+    }
+
+    #[test]
+    fn profile_settings() {
+        let args = command_line(CONFIG, &["connect-box", "serve"]);
+        let matches = cli::build_cli().get_matches_from(args);
+        assert_eq!(matches.value_of("host"), Some("192.168.0.1"));
+        let serve = matches.subcommand_matches("serve").unwrap();
+        assert_eq!(serve.value_of("listen"), Some("127.0.0.1:8080"));
+        assert_eq!(serve.value_of("basic-auth"), Some("admin:secret"));
+    }
+
+    #[test]
+    fn subcommand_settings_only_apply_to_it() {
+        let args = command_line(CONFIG, &["connect-box", "reboot"]);
+        assert_eq!(
+            args,
+            [
+                "connect-box",
+                "--host=192.168.0.1",
+                "--password-file=/etc/connect-box/password",
+                "reboot"
+            ]
+        );
+    }
+
+    #[test]
+    fn command_line_overrides_profile() {
+        let args = command_line(
+            CONFIG,
+            &[
+                "connect-box",
+                "--host=10.0.0.1",
+                "--password-keyring",
+                "serve",
+                "--listen=127.0.0.1:9090",
+            ],
+        );
+        let matches = cli::build_cli().get_matches_from(args);
+        assert_eq!(matches.value_of("host"), Some("10.0.0.1"));
+        assert!(matches.is_present("password-keyring"));
+        assert!(!matches.is_present("password-file"));
+        let serve = matches.subcommand_matches("serve").unwrap();
+        assert_eq!(serve.value_of("listen"), Some("127.0.0.1:9090"));
+        assert_eq!(serve.value_of("basic-auth"), Some("admin:secret"));
+    }
+
+    #[test]
+    fn valid_profile() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        assert_eq!(validate(&config.profile["home"]), Ok(()));
+        // The MAC address of block is only given on the command line.
+        assert_eq!(validate_str("[block]"), Ok(()));
+    }
+
+    #[test]
+    fn invalid_subcommand_settings() {
+        assert!(validate_str("[serve]\nlisten = \"bad\"").is_err());
+        assert!(validate_str("[serve]\nunknown = true").is_err());
+        assert!(validate_str("[reboot]\nlisten = \"127.0.0.1:8080\"").is_err());
+        assert_eq!(
+            validate_str("[unknown]\nlisten = \"127.0.0.1:8080\""),
+            Err("Unknown subcommand \"unknown\"".to_owned())
+        );
+        assert!(validate_str("[config]").is_err());
+    }
+}
@@ -1,5 +1,8 @@
 use crate::router::Router;
-use crate::types::{CmState, LanUserTable};
+use crate::types::{
+    CmState, CmSystemInfo, DocsisChannels, DownstreamTable, GlobalSettings, LanUserTable, MacAddr,
+    UpstreamTable,
+};
 use async_trait::async_trait;
 use futures::stream;
 use futures::stream::{Stream, StreamExt};
@@ -12,17 +15,18 @@ use std::marker::Unpin;
 use std::net::Ipv4Addr;
 use tokio::time;
 use tokio::time::Throttle;
+use zeroize::Zeroizing;
 
-pub struct ConnectBox<'a> {
+pub struct ConnectBox {
     client: Client,
     addr: Ipv4Addr,
-    password: &'a str,
+    password: Zeroizing<String>,
     token: String,
     throttle_duration: time::Duration,
 }
 
 #[async_trait(?Send)]
-impl<'a> Router for ConnectBox<'a> {
+impl Router for ConnectBox {
     async fn logout(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         self.set(ConnectBox::CMD_LOGOUT, vec![]).await?;
         Ok(())
@@ -41,6 +45,50 @@ impl<'a> Router for ConnectBox<'a> {
         let result = serde_xml_rs::from_str(&xml)?;
         Ok(result)
     }
+
+    async fn global_settings(&mut self) -> Result<GlobalSettings, Box<dyn std::error::Error>> {
+        let xml = self.get(ConnectBox::CMD_GLOBAL_SETTINGS).await?;
+        trace!("XML: {}", xml);
+        let result = serde_xml_rs::from_str(&xml)?;
+        Ok(result)
+    }
+
+    async fn system_info(&mut self) -> Result<CmSystemInfo, Box<dyn std::error::Error>> {
+        let xml = self.get(ConnectBox::CMD_SYSTEM_INFO).await?;
+        trace!("XML: {}", xml);
+        let result = serde_xml_rs::from_str(&xml)?;
+        Ok(result)
+    }
+
+    async fn channels(&mut self) -> Result<DocsisChannels, Box<dyn std::error::Error>> {
+        let xml = self.get(ConnectBox::CMD_DOWNSTREAM).await?;
+        trace!("XML: {}", xml);
+        let downstream: DownstreamTable = serde_xml_rs::from_str(&xml)?;
+
+        let xml = self.get(ConnectBox::CMD_UPSTREAM).await?;
+        trace!("XML: {}", xml);
+        let upstream: UpstreamTable = serde_xml_rs::from_str(&xml)?;
+
+        Ok(DocsisChannels {
+            downstream: downstream.channels,
+            upstream: upstream.channels,
+        })
+    }
+
+    async fn block_device(&mut self, mac: &MacAddr) -> Result<(), Box<dyn std::error::Error>> {
+        let mac = mac.to_string();
+        self.set(
+            ConnectBox::CMD_MAC_FILTER,
+            vec![("action", "add"), ("MACAddr", &mac)],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn reboot(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.set(ConnectBox::CMD_REBOOT, vec![]).await?;
+        Ok(())
+    }
 }
 
 // Helper trait for implementing a generic retry loop. See:
@@ -59,18 +107,24 @@ where
     type Output = Fut::Output;
 }
 
-impl<'a> ConnectBox<'a> {
+impl ConnectBox {
+    const CMD_GLOBAL_SETTINGS: usize = 1;
+    const CMD_SYSTEM_INFO: usize = 2;
+    const CMD_REBOOT: usize = 8;
+    const CMD_DOWNSTREAM: usize = 10;
+    const CMD_UPSTREAM: usize = 11;
     const CMD_LOGIN: usize = 15;
     const CMD_LOGOUT: usize = 16;
     const CMD_DEVICES: usize = 123;
     const CMD_TEMPERATURE: usize = 136;
+    const CMD_MAC_FILTER: usize = 120;
 
     pub async fn new(
         addr: Ipv4Addr,
-        password: &'a str,
+        password: Zeroizing<String>,
         timeout_duration: time::Duration,
         throttle_duration: time::Duration,
-    ) -> Result<ConnectBox<'a>, Box<dyn std::error::Error>> {
+    ) -> Result<ConnectBox, Box<dyn std::error::Error>> {
         let client = Client::builder()
             .user_agent("Mozilla/5.0")
             .cookie_store(true)
@@ -114,10 +168,11 @@ impl<'a> ConnectBox<'a> {
 
     async fn login(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         debug!("Logging in...");
+        let password = self.password.clone();
         let text = self
             .set(
                 ConnectBox::CMD_LOGIN,
-                vec![("Username", "NULL"), ("Password", self.password)],
+                vec![("Username", "NULL"), ("Password", &password)],
             )
             .await?;
 
@@ -268,6 +323,7 @@ impl<'a> ConnectBox<'a> {
     }
 
     // TODO: don't retry indefinitely after interrupt.
+    #[allow(clippy::borrowed_box)]
     async fn should_retry(
         error: &Box<dyn std::error::Error>,
         throttle: &mut Throttle<impl Stream + Unpin>,
@@ -0,0 +1,317 @@
+use crate::types::{diff_clients, ClientChange, ClientInfo};
+use std::time::{Duration, Instant};
+
+// Heuristic to recognize devices that use a randomized MAC address. Such devices show up as a new
+// client every time they rotate their address, so we try to match each newly added client that
+// has a locally-administered MAC address with a client that disappeared, either in the same
+// refresh or earlier on.
+//
+// Common hostnames (e.g. "iPhone") aren't enough to tell devices apart, and neither is the
+// interface, since many devices share the same WiFi band. So two clients are considered to be
+// the same device if they share the same (non-empty) hostname, and either:
+// - their IPv6 addresses have the same interface identifier (lower 64 bits), or
+// - they have the same IPv4 address, which the DHCP server keeps for a device until its lease
+//   expires, through the same kind of interface (since a released address may be handed to
+//   another device).
+// The departed clients are only remembered for a short while (see `Correlator`), since a device
+// that rotates its address reconnects right away.
+pub fn correlate<'a>(
+    changes: Vec<ClientChange<'a>>,
+    departed: &'a [ClientInfo],
+) -> Vec<ClientChange<'a>> {
+    let mut removed: Vec<Option<&ClientInfo>> = changes
+        .iter()
+        .map(|change| match change {
+            ClientChange::Removed(o) => Some(*o),
+            _ => None,
+        })
+        .collect();
+    let mut departed: Vec<Option<&ClientInfo>> = departed.iter().map(Some).collect();
+
+    let mut matches: Vec<Option<&ClientInfo>> = Vec::with_capacity(changes.len());
+    for change in changes.iter() {
+        let matched = match change {
+            ClientChange::Added(n) if n.mac.is_locally_administered() => {
+                find_same_device(&mut removed, n).or_else(|| find_same_device(&mut departed, n))
+            }
+            _ => None,
+        };
+        matches.push(matched);
+    }
+
+    changes
+        .into_iter()
+        .zip(matches)
+        .enumerate()
+        .filter_map(|(i, (change, matched))| match (change, matched) {
+            (ClientChange::Added(new), Some(old)) => Some(ClientChange::Reconnected { old, new }),
+            (ClientChange::Removed(_), _) if removed[i].is_none() => None,
+            (change, _) => Some(change),
+        })
+        .collect()
+}
+
+/// Keeps track of recently departed devices, so that they can be recognized if they reconnect
+/// with a new randomized MAC address.
+pub struct Correlator {
+    departed: Vec<ClientInfo>,
+    // When each departed device left, in the same order.
+    departure_times: Vec<Instant>,
+}
+
+impl Correlator {
+    // Maximal number of departed devices to remember.
+    const MAX_DEPARTED: usize = 64;
+    // How long a departed device is remembered.
+    const RECONNECT_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+    pub fn new() -> Self {
+        Self {
+            departed: Vec::new(),
+            departure_times: Vec::new(),
+        }
+    }
+
+    pub fn departed(&self) -> &[ClientInfo] {
+        &self.departed
+    }
+
+    pub fn update(&mut self, old: &[ClientInfo], new: &[ClientInfo]) {
+        self.update_at(old, new, Instant::now());
+    }
+
+    fn update_at(&mut self, old: &[ClientInfo], new: &[ClientInfo], now: Instant) {
+        let mut reconnected = Vec::new();
+        let mut removed = Vec::new();
+        for change in correlate(diff_clients(old, new), &self.departed) {
+            match change {
+                ClientChange::Removed(o) => removed.push(o.clone()),
+                ClientChange::Reconnected { old, .. } => reconnected.push(old.mac),
+                ClientChange::Added(_) | ClientChange::Updated { .. } => (),
+            }
+        }
+
+        let departed = std::mem::take(&mut self.departed);
+        let departure_times = std::mem::take(&mut self.departure_times);
+        for (client, time) in departed.into_iter().zip(departure_times) {
+            if !reconnected.contains(&client.mac)
+                && now.duration_since(time) <= Self::RECONNECT_WINDOW
+            {
+                self.departed.push(client);
+                self.departure_times.push(time);
+            }
+        }
+        self.departure_times.extend(removed.iter().map(|_| now));
+        self.departed.extend(removed);
+        if self.departed.len() > Self::MAX_DEPARTED {
+            let excess = self.departed.len() - Self::MAX_DEPARTED;
+            self.departed.drain(..excess);
+            self.departure_times.drain(..excess);
+        }
+    }
+}
+
+fn find_same_device<'a>(
+    candidates: &mut [Option<&'a ClientInfo>],
+    client: &ClientInfo,
+) -> Option<&'a ClientInfo> {
+    candidates
+        .iter()
+        .position(|o| o.map_or(false, |o| is_same_device(o, client)))
+        .and_then(|i| candidates[i].take())
+}
+
+fn is_same_device(old: &ClientInfo, new: &ClientInfo) -> bool {
+    if old.hostname.is_empty() || old.hostname != new.hostname {
+        return false;
+    }
+
+    let same_interface_id = match (interface_id(old), interface_id(new)) {
+        (Some(o), Some(n)) => o == n,
+        _ => false,
+    };
+    let same_ipv4 =
+        old.ipv4.is_some() && old.ipv4 == new.ipv4 && old.interface_kind() == new.interface_kind();
+
+    same_interface_id || same_ipv4
+}
+
+// Extracts the interface identifier (lower 64 bits) of the client's IPv6 address.
+fn interface_id(client: &ClientInfo) -> Option<u64> {
+    client.ipv6.map(|addr| u128::from(addr) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AddressMethod;
+
+    // Interface names, as shown by the router.
+    const ETHERNET: &str = "Ethernet 1";
+    const WIFI_2_4GHZ: &str = "Wi-Fi 2.4G";
+    const WIFI_5GHZ: &str = "Wi-Fi 5G";
+
+    fn client(mac: &str, hostname: &str, interface: &str) -> ClientInfo {
+        ClientInfo {
+            mac: mac.parse().unwrap(),
+            hostname: hostname.to_owned(),
+            index: 0,
+            ipv4: None,
+            ipv6: None,
+            interface: interface.to_owned(),
+            interfaceid: 0,
+            method: AddressMethod::Dhcp,
+            lease_time: Duration::from_secs(3600),
+            speed: 0,
+        }
+    }
+
+    // Changes as (kind, MAC address) pairs, for comparison.
+    fn summary(changes: &[ClientChange]) -> Vec<(&'static str, String)> {
+        changes
+            .iter()
+            .map(|change| match change {
+                ClientChange::Added(n) => ("added", n.mac.to_string()),
+                ClientChange::Removed(o) => ("removed", o.mac.to_string()),
+                ClientChange::Updated { new, .. } => ("updated", new.mac.to_string()),
+                ClientChange::Reconnected { old, new } => {
+                    ("reconnected", format!("{} -> {}", old.mac, new.mac))
+                }
+            })
+            .collect()
+    }
+
+    fn with_ipv4(mut client: ClientInfo, ipv4: &str) -> ClientInfo {
+        client.ipv4 = Some(ipv4.parse().unwrap());
+        client
+    }
+
+    #[test]
+    fn rotated_address_in_same_refresh() {
+        let old = [with_ipv4(
+            client("02:00:00:00:00:01", "phone", WIFI_5GHZ),
+            "192.168.0.42",
+        )];
+        let new = [with_ipv4(
+            client("02:00:00:00:00:02", "phone", WIFI_5GHZ),
+            "192.168.0.42",
+        )];
+        let changes = correlate(diff_clients(&old, &new), &[]);
+        assert_eq!(
+            summary(&changes),
+            [(
+                "reconnected",
+                "02:00:00:00:00:01 -> 02:00:00:00:00:02".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn rotated_address_after_departure() {
+        let departed = [with_ipv4(
+            client("02:00:00:00:00:01", "phone", WIFI_5GHZ),
+            "192.168.0.42",
+        )];
+        let new = [with_ipv4(
+            client("02:00:00:00:00:02", "phone", WIFI_5GHZ),
+            "192.168.0.42",
+        )];
+        let changes = correlate(diff_clients(&[], &new), &departed);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0], ClientChange::Reconnected { .. }));
+    }
+
+    #[test]
+    fn same_hostname_alone_is_not_enough() {
+        let departed = [client("02:00:00:00:00:01", "iPhone", WIFI_5GHZ)];
+        let new = [client("02:00:00:00:00:02", "iPhone", WIFI_2_4GHZ)];
+        let changes = correlate(diff_clients(&[], &new), &departed);
+        assert_eq!(
+            summary(&changes),
+            [("added", "02:00:00:00:00:02".to_owned())]
+        );
+    }
+
+    #[test]
+    fn same_hostname_and_band_is_not_enough() {
+        let mut old = client("02:00:00:00:00:01", "iPhone", WIFI_5GHZ);
+        old.ipv4 = Some("192.168.0.42".parse().unwrap());
+        old.ipv6 = Some("2001:db8::1234:5678:9abc:def0".parse().unwrap());
+        let mut new = client("02:00:00:00:00:02", "iPhone", WIFI_5GHZ);
+        new.ipv4 = Some("192.168.0.43".parse().unwrap());
+        new.ipv6 = Some("2001:db8::1234:5678:9abc:def1".parse().unwrap());
+        let (old, new) = ([old], [new]);
+        let changes = correlate(diff_clients(&old, &new), &[]);
+        assert_eq!(changes.len(), 2);
+        let changes = correlate(diff_clients(&[], &new), &old);
+        assert_eq!(
+            summary(&changes),
+            [("added", "02:00:00:00:00:02".to_owned())]
+        );
+    }
+
+    #[test]
+    fn same_ipv4_through_another_interface() {
+        let departed = [with_ipv4(
+            client("02:00:00:00:00:01", "phone", WIFI_5GHZ),
+            "192.168.0.42",
+        )];
+        let new = [with_ipv4(
+            client("02:00:00:00:00:02", "phone", ETHERNET),
+            "192.168.0.42",
+        )];
+        let changes = correlate(diff_clients(&[], &new), &departed);
+        assert_eq!(
+            summary(&changes),
+            [("added", "02:00:00:00:00:02".to_owned())]
+        );
+    }
+
+    #[test]
+    fn same_ipv6_interface_id() {
+        let mut old = client("02:00:00:00:00:01", "phone", WIFI_5GHZ);
+        old.ipv6 = Some("2001:db8::1234:5678:9abc:def0".parse().unwrap());
+        let mut new = client("02:00:00:00:00:02", "phone", WIFI_2_4GHZ);
+        new.ipv6 = Some("2001:db8:0:1:1234:5678:9abc:def0".parse().unwrap());
+        let (departed, new) = ([old], [new]);
+        let changes = correlate(diff_clients(&[], &new), &departed);
+        assert!(matches!(changes[0], ClientChange::Reconnected { .. }));
+    }
+
+    #[test]
+    fn universal_addresses_are_not_correlated() {
+        let departed = [client("00:11:22:33:44:55", "laptop", ETHERNET)];
+        let new = [client("00:11:22:33:44:66", "laptop", ETHERNET)];
+        let changes = correlate(diff_clients(&[], &new), &departed);
+        assert_eq!(
+            summary(&changes),
+            [("added", "00:11:22:33:44:66".to_owned())]
+        );
+    }
+
+    #[test]
+    fn empty_hostnames_are_not_correlated() {
+        let old = [client("02:00:00:00:00:01", "", WIFI_5GHZ)];
+        let new = [client("02:00:00:00:00:02", "", WIFI_5GHZ)];
+        let changes = correlate(diff_clients(&old, &new), &[]);
+        assert_eq!(changes.len(), 2);
+    }
+
+    #[test]
+    fn departed_devices_are_forgotten() {
+        let phone = client("02:00:00:00:00:01", "phone", WIFI_5GHZ);
+        let start = Instant::now();
+        let mut correlator = Correlator::new();
+        correlator.update_at(&[phone], &[], start);
+        assert_eq!(correlator.departed().len(), 1);
+
+        correlator.update_at(&[], &[], start + Correlator::RECONNECT_WINDOW);
+        assert_eq!(correlator.departed().len(), 1);
+        correlator.update_at(
+            &[],
+            &[],
+            start + Correlator::RECONNECT_WINDOW + Duration::from_secs(1),
+        );
+        assert!(correlator.departed().is_empty());
+    }
+}
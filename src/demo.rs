@@ -1,10 +1,24 @@
 use crate::router::Router;
-use crate::types::{ClientInfo, CmState, LanUserTable, WanIpv6Addr, Wifi};
+use crate::types::{
+    parse_lease_time, AddressMethod, ClientInfo, CmState, CmSystemInfo, DocsisChannels,
+    DownstreamChannel, GlobalSettings, LanUserTable, MacAddr, UpstreamChannel, WanIpv6Addr, Wifi,
+};
 use async_trait::async_trait;
+use std::time::{Duration, Instant};
 
 pub struct DemoRouter {
     states: Vec<LanUserTable>,
     i: usize,
+    temperature_i: usize,
+    channels_i: u64,
+    blocked: Vec<MacAddr>,
+    // Uptime of the modem when the demo started or was rebooted.
+    start: Instant,
+    start_uptime: Duration,
+}
+
+fn lease(lease_time: &str) -> Duration {
+    parse_lease_time(lease_time).unwrap()
 }
 
 impl DemoRouter {
@@ -15,39 +29,39 @@ impl DemoRouter {
         // State #0
         let mut clients = vec![
             ClientInfo {
-                mac: "AB:CD:EF:01:23:45".to_owned(),
+                mac: "AB:CD:EF:01:23:45".parse().unwrap(),
                 hostname: "laptop".to_owned(),
                 index: 0,
-                ipv4: Some("192.168.0.1".to_owned()),
+                ipv4: Some("192.168.0.1".parse().unwrap()),
                 ipv6: None,
-                interface: "foo".to_owned(),
-                interfaceid: 0,
-                method: 0,
-                lease_time: "00:00:47:57".to_owned(),
+                interface: "Wi-Fi 5G".to_owned(),
+                interfaceid: 3,
+                method: AddressMethod::Dhcp,
+                lease_time: lease("00:00:47:57"),
                 speed: 1,
             },
             ClientInfo {
-                mac: "CD:EF:01:23:45:AB".to_owned(),
+                mac: "CD:EF:01:23:45:AB".parse().unwrap(),
                 hostname: "My Super Phone".to_owned(),
                 index: 0,
-                ipv4: Some("192.168.0.42".to_owned()),
-                ipv6: Some("2001:2345:6789:abcd:ef01:1010:3564:2".to_owned()),
-                interface: "foo".to_owned(),
-                interfaceid: 0,
-                method: 0,
-                lease_time: "00:00:38:18".to_owned(),
+                ipv4: Some("192.168.0.42".parse().unwrap()),
+                ipv6: Some("2001:2345:6789:abcd:ef01:1010:3564:2".parse().unwrap()),
+                interface: "Wi-Fi 2.4G".to_owned(),
+                interfaceid: 2,
+                method: AddressMethod::Dhcp,
+                lease_time: lease("00:00:04:18"),
                 speed: 123,
             },
             ClientInfo {
-                mac: "EF:01:23:45:AB:CD".to_owned(),
+                mac: "EF:01:23:45:AB:CD".parse().unwrap(),
                 hostname: "Desktop".to_owned(),
                 index: 0,
                 ipv4: None,
-                ipv6: Some("2001:2345:6789:abcd:ef01:1010:3564:5".to_owned()),
-                interface: "foo".to_owned(),
-                interfaceid: 0,
-                method: 0,
-                lease_time: "00:00:52:45".to_owned(),
+                ipv6: Some("2001:2345:6789:abcd:ef01:1010:3564:5".parse().unwrap()),
+                interface: "Ethernet 1".to_owned(),
+                interfaceid: 1,
+                method: AddressMethod::Dhcp,
+                lease_time: lease("00:00:52:45"),
                 speed: 42,
             },
         ];
@@ -61,10 +75,10 @@ impl DemoRouter {
         });
 
         // State #1
-        clients[0].lease_time = "00:00:47:54".to_owned();
-        clients[1].lease_time = "00:00:38:15".to_owned();
+        clients[0].lease_time = lease("00:00:47:54");
+        clients[1].lease_time = lease("00:00:04:15");
         clients[1].speed = 234;
-        clients[2].lease_time = "00:00:52:42".to_owned();
+        clients[2].lease_time = lease("00:00:52:42");
         clients[2].speed = 23;
 
         states.push(LanUserTable {
@@ -76,12 +90,12 @@ impl DemoRouter {
         });
 
         // State #2
-        clients[0].lease_time = "00:00:47:51".to_owned();
+        clients[0].lease_time = lease("00:00:47:51");
         clients[0].speed = 17;
-        clients[0].ipv6 = Some("2001:2345:6789:abcd:ef01:1010:3564:888".to_owned());
-        clients[1].lease_time = "00:00:38:12".to_owned();
+        clients[0].ipv6 = Some("2001:2345:6789:abcd:ef01:1010:3564:888".parse().unwrap());
+        clients[1].lease_time = lease("00:00:04:12");
         clients[1].ipv4 = None;
-        clients[2].lease_time = "00:00:52:39".to_owned();
+        clients[2].lease_time = lease("00:00:52:39");
         clients[2].speed = 67;
 
         states.push(LanUserTable {
@@ -93,9 +107,9 @@ impl DemoRouter {
         });
 
         // State #3
-        clients[0].lease_time = "00:00:47:48".to_owned();
-        clients[1].lease_time = "00:00:38:09".to_owned();
-        clients[2].lease_time = "00:00:52:36".to_owned();
+        clients[0].lease_time = lease("00:00:47:48");
+        clients[1].lease_time = lease("00:00:04:09");
+        clients[2].lease_time = lease("00:00:52:36");
 
         states.push(LanUserTable {
             customer: "Customer".to_owned(),
@@ -106,8 +120,8 @@ impl DemoRouter {
         });
 
         // State #4
-        clients[0].lease_time = "00:00:47:45".to_owned();
-        clients[2].lease_time = "00:00:52:33".to_owned();
+        clients[0].lease_time = lease("00:00:47:45");
+        clients[2].lease_time = lease("00:00:52:33");
         clients[2].speed = 128;
         clients.remove(1);
 
@@ -120,8 +134,9 @@ impl DemoRouter {
         });
 
         // State #5
-        clients[0].lease_time = "00:00:47:42".to_owned();
-        clients[1].lease_time = "00:00:52:30".to_owned();
+        clients[0].lease_time = lease("00:00:47:42");
+        // The desktop renews its lease.
+        clients[1].lease_time = lease("00:00:59:59");
 
         states.push(LanUserTable {
             customer: "Customer".to_owned(),
@@ -132,18 +147,18 @@ impl DemoRouter {
         });
 
         // State #6
-        clients[0].lease_time = "00:00:47:39".to_owned();
-        clients[1].lease_time = "00:00:52:27".to_owned();
+        clients[0].lease_time = lease("00:00:47:39");
+        clients[1].lease_time = lease("00:00:59:56");
         clients.push(ClientInfo {
-            mac: "01:23:45:AB:CD:EF".to_owned(),
+            mac: "01:23:45:AB:CD:EF".parse().unwrap(),
             hostname: "Connected TV".to_owned(),
             index: 0,
-            ipv4: Some("192.168.0.123".to_owned()),
+            ipv4: Some("192.168.0.123".parse().unwrap()),
             ipv6: None,
-            interface: "foo".to_owned(),
-            interfaceid: 0,
-            method: 0,
-            lease_time: "00:00:59:59".to_owned(),
+            interface: "Ethernet 2".to_owned(),
+            interfaceid: 1,
+            method: AddressMethod::Dhcp,
+            lease_time: lease("00:00:59:59"),
             speed: 123,
         });
 
@@ -156,9 +171,22 @@ impl DemoRouter {
         });
 
         // State #7
-        clients[0].lease_time = "00:00:47:36".to_owned();
-        clients[1].lease_time = "00:00:52:24".to_owned();
-        clients[2].lease_time = "00:00:59:56".to_owned();
+        clients[0].lease_time = lease("00:00:47:36");
+        clients[1].lease_time = lease("00:00:59:53");
+        clients[2].lease_time = lease("00:00:59:56");
+        // The phone comes back with a randomized MAC address.
+        clients.push(ClientInfo {
+            mac: "DA:12:34:56:78:9A".parse().unwrap(),
+            hostname: "My Super Phone".to_owned(),
+            index: 0,
+            ipv4: Some("192.168.0.43".parse().unwrap()),
+            ipv6: Some("2001:2345:6789:abcd:ef01:1010:3564:2".parse().unwrap()),
+            interface: "Wi-Fi 5G".to_owned(),
+            interfaceid: 3,
+            method: AddressMethod::Dhcp,
+            lease_time: lease("00:00:59:59"),
+            speed: 234,
+        });
 
         states.push(LanUserTable {
             customer: "Customer".to_owned(),
@@ -169,7 +197,15 @@ impl DemoRouter {
         });
 
         // Finalize
-        DemoRouter { states, i: 0 }
+        DemoRouter {
+            states,
+            i: 0,
+            temperature_i: 0,
+            channels_i: 0,
+            blocked: Vec::new(),
+            start: Instant::now(),
+            start_uptime: Duration::from_secs(12 * 86400 + 5 * 3600),
+        }
     }
 }
 
@@ -180,19 +216,109 @@ impl Router for DemoRouter {
     }
 
     async fn devices(&mut self) -> Result<LanUserTable, Box<dyn std::error::Error>> {
-        let state = self.states[self.i].clone();
+        let mut state = self.states[self.i].clone();
         self.i = (self.i + 1) % self.states.len();
+
+        let blocked = &self.blocked;
+        state
+            .wifi
+            .clientinfo
+            .retain(|client| !blocked.contains(&client.mac));
+        state.total_client = state.wifi.clientinfo.len();
         Ok(state)
     }
 
     async fn temperature(&mut self) -> Result<CmState, Box<dyn std::error::Error>> {
+        const TEMPERATURES: [usize; 6] = [34, 35, 37, 38, 36, 35];
+        let temperature = TEMPERATURES[self.temperature_i];
+        self.temperature_i = (self.temperature_i + 1) % TEMPERATURES.len();
+
         Ok(CmState {
-            tunner_temperature: 12,
-            temperature: 34,
-            oper_state: "foo".to_owned(),
+            tunner_temperature: temperature + 8,
+            temperature,
+            oper_state: "OPERATIONAL".to_owned(),
+            wan_ipv4_addr: Some("203.0.113.42".parse().unwrap()),
             wan_ipv6_addr: WanIpv6Addr {
                 addresses: vec!["2001:2345:6789:abcd::1".to_owned()],
             },
         })
     }
+
+    async fn global_settings(&mut self) -> Result<GlobalSettings, Box<dyn std::error::Error>> {
+        Ok(GlobalSettings {
+            model: "CH7465LG".to_owned(),
+            sw_version: "CH7465LG-NCIP-6.15.30-1p3-NOSH".to_owned(),
+            cm_provision_mode: "IPv4".to_owned(),
+            gw_provision_mode: "IPv4".to_owned(),
+            operator_id: "DEMO".to_owned(),
+        })
+    }
+
+    async fn system_info(&mut self) -> Result<CmSystemInfo, Box<dyn std::error::Error>> {
+        Ok(CmSystemInfo {
+            docsis_mode: "DOCSIS 3.0".to_owned(),
+            hardware_version: "5.01".to_owned(),
+            mac: "01:23:45:67:89:AB".to_owned(),
+            serial_number: "DEMO00000000".to_owned(),
+            uptime: self.start_uptime + self.start.elapsed(),
+            network_access: "Allowed".to_owned(),
+        })
+    }
+
+    async fn channels(&mut self) -> Result<DocsisChannels, Box<dyn std::error::Error>> {
+        let i = self.channels_i;
+        self.channels_i += 1;
+        // Small variations of the signal between refreshes.
+        let wobble = |channel: u64| ((i + channel) % 5) as f64 * 0.2 - 0.4;
+
+        // Channel #6 has a weak signal, and accumulates uncorrectable errors.
+        let downstream = (1..=8)
+            .map(|id| {
+                let weak = id == 6;
+                DownstreamChannel {
+                    id: id as usize,
+                    frequency: 594_000_000 + 8_000_000 * id,
+                    power: if weak { -9.0 } else { 3.5 } + wobble(id),
+                    snr: if weak { 31.5 } else { 38.6 } + wobble(id),
+                    modulation: "256qam".to_owned(),
+                    corrected: i * if weak { 120 } else { 3 },
+                    uncorrectable: if weak { i * i } else { 0 },
+                    qam_locked: true,
+                    fec_locked: true,
+                    mpeg_locked: !weak || i % 4 != 3,
+                }
+            })
+            .collect();
+        let upstream = (1..=4)
+            .map(|id| UpstreamChannel {
+                id: id as usize,
+                frequency: 30_800_000 + 6_400_000 * id,
+                power: if id == 4 { 51.5 } else { 44.0 } + wobble(id),
+                symbol_rate: 5120.0,
+                modulation: "64qam".to_owned(),
+                t1_timeouts: 0,
+                t2_timeouts: 0,
+                t3_timeouts: if id == 4 { i / 3 } else { 0 },
+                t4_timeouts: 0,
+            })
+            .collect();
+
+        Ok(DocsisChannels {
+            downstream,
+            upstream,
+        })
+    }
+
+    async fn block_device(&mut self, mac: &MacAddr) -> Result<(), Box<dyn std::error::Error>> {
+        self.blocked.push(*mac);
+        Ok(())
+    }
+
+    async fn reboot(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        // The uptime and the error counters of the channels start over.
+        self.start = Instant::now();
+        self.start_uptime = Duration::from_secs(0);
+        self.channels_i = 0;
+        Ok(())
+    }
 }
@@ -0,0 +1,138 @@
+use crate::types::{DocsisChannels, DownstreamChannel};
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+/// How a signal measurement compares to the acceptable DOCSIS ranges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+    Good,
+    Marginal,
+    Bad,
+}
+
+impl Quality {
+    // Classifies a value against nested ranges, the inner one being the good range.
+    fn of(value: f64, good: (f64, f64), acceptable: (f64, f64)) -> Self {
+        if value >= good.0 && value <= good.1 {
+            Quality::Good
+        } else if value >= acceptable.0 && value <= acceptable.1 {
+            Quality::Marginal
+        } else {
+            Quality::Bad
+        }
+    }
+}
+
+/// Received power of a downstream channel, in dBmV.
+pub fn downstream_power_quality(power: f64) -> Quality {
+    Quality::of(power, (-7.0, 7.0), (-15.0, 15.0))
+}
+
+/// Signal to noise ratio of a downstream channel. Denser modulations need a cleaner signal.
+pub fn downstream_snr_quality(channel: &DownstreamChannel) -> Quality {
+    let (good, acceptable) = if channel.modulation.contains("256") {
+        (33.0, 30.0)
+    } else {
+        (27.0, 24.0)
+    };
+    Quality::of(
+        channel.snr,
+        (good, f64::INFINITY),
+        (acceptable, f64::INFINITY),
+    )
+}
+
+/// Transmit power of an upstream channel, in dBmV.
+pub fn upstream_power_quality(power: f64) -> Quality {
+    Quality::of(power, (35.0, 49.0), (30.0, 52.0))
+}
+
+/// Recent measurements of a downstream channel.
+pub struct DownstreamHistory {
+    pub power: VecDeque<f64>,
+    pub snr: VecDeque<f64>,
+    /// Corrected and uncorrectable codewords per minute, since the previous refresh.
+    pub error_rates: Option<(f64, f64)>,
+    // Time and codeword error counters at the previous refresh.
+    last: (Instant, u64, u64),
+}
+
+/// Recent measurements of an upstream channel.
+pub struct UpstreamHistory {
+    pub power: VecDeque<f64>,
+}
+
+/// Rolling history of the DOCSIS channel measurements, by channel ID.
+pub struct ChannelHistory {
+    downstream: HashMap<usize, DownstreamHistory>,
+    upstream: HashMap<usize, UpstreamHistory>,
+}
+
+impl ChannelHistory {
+    // Number of samples kept for each channel.
+    const MAX_SAMPLES: usize = 120;
+
+    pub fn new() -> Self {
+        Self {
+            downstream: HashMap::new(),
+            upstream: HashMap::new(),
+        }
+    }
+
+    pub fn downstream(&self, id: usize) -> Option<&DownstreamHistory> {
+        self.downstream.get(&id)
+    }
+
+    pub fn upstream(&self, id: usize) -> Option<&UpstreamHistory> {
+        self.upstream.get(&id)
+    }
+
+    pub fn update(&mut self, channels: &DocsisChannels) {
+        let now = Instant::now();
+        for channel in &channels.downstream {
+            let history = self
+                .downstream
+                .entry(channel.id)
+                .or_insert_with(|| DownstreamHistory {
+                    power: VecDeque::new(),
+                    snr: VecDeque::new(),
+                    error_rates: None,
+                    last: (now, channel.corrected, channel.uncorrectable),
+                });
+
+            let (time, corrected, uncorrectable) = history.last;
+            let minutes = now.duration_since(time).as_secs_f64() / 60.0;
+            // The counters are reset when the modem restarts.
+            if minutes > 0.0
+                && channel.corrected >= corrected
+                && channel.uncorrectable >= uncorrectable
+            {
+                history.error_rates = Some((
+                    (channel.corrected - corrected) as f64 / minutes,
+                    (channel.uncorrectable - uncorrectable) as f64 / minutes,
+                ));
+            }
+            history.last = (now, channel.corrected, channel.uncorrectable);
+
+            push_sample(&mut history.power, channel.power);
+            push_sample(&mut history.snr, channel.snr);
+        }
+
+        for channel in &channels.upstream {
+            let history = self
+                .upstream
+                .entry(channel.id)
+                .or_insert_with(|| UpstreamHistory {
+                    power: VecDeque::new(),
+                });
+            push_sample(&mut history.power, channel.power);
+        }
+    }
+}
+
+fn push_sample(samples: &mut VecDeque<f64>, value: f64) {
+    samples.push_back(value);
+    if samples.len() > ChannelHistory::MAX_SAMPLES {
+        samples.pop_front();
+    }
+}
@@ -0,0 +1,418 @@
+use crate::types::{ClientInfo, CmState, DocsisChannels, LanUserTable, MacAddr};
+use chrono::{DateTime, Local, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Maximal duration between successive device tables, beyond which monitoring is considered to
+/// have been interrupted.
+pub const MAX_GAP: chrono::Duration = chrono::Duration::minutes(10);
+
+/// A timestamped poll result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub time: DateTime<Utc>,
+    #[serde(flatten)]
+    pub data: RecordData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "lowercase")]
+pub enum RecordData {
+    Devices(LanUserTable),
+    Temperature(CmState),
+    Channels(DocsisChannels),
+}
+
+/// Append-only store of poll results, as a file of JSON lines. Records older than the retention
+/// period are periodically pruned.
+pub struct HistoryStore {
+    path: PathBuf,
+    retention: Duration,
+    file: File,
+    last_compaction: Instant,
+}
+
+impl HistoryStore {
+    // Period between compactions of the store.
+    const COMPACTION_PERIOD: Duration = Duration::from_secs(3600);
+
+    pub fn open(path: &Path, retention: Duration) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        compact(path, retention)?;
+        Ok(Self {
+            path: path.to_owned(),
+            retention,
+            file: open_append(path)?,
+            last_compaction: Instant::now(),
+        })
+    }
+
+    pub fn append(&mut self, data: RecordData) -> Result<(), Box<dyn std::error::Error>> {
+        if self.last_compaction.elapsed() > Self::COMPACTION_PERIOD {
+            compact(&self.path, self.retention)?;
+            self.file = open_append(&self.path)?;
+            self.last_compaction = Instant::now();
+        }
+
+        let record = Record {
+            time: Utc::now(),
+            data,
+        };
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Default location of the history file.
+pub fn default_path() -> PathBuf {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    data_dir.join("connect-box").join("history.jsonl")
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Reads all the records since the given time. Corrupted lines are skipped.
+pub fn read(path: &Path, since: DateTime<Utc>) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Box::new(e)),
+    };
+
+    let mut records = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        match serde_json::from_str::<Record>(&line) {
+            Ok(record) => {
+                if record.time >= since {
+                    records.push(record);
+                }
+            }
+            Err(e) => warn!("Skipping invalid history record at line {}: {}", i + 1, e),
+        }
+    }
+    Ok(records)
+}
+
+// Removes the records older than the retention period, and the corrupted lines. The records are
+// streamed to a temporary file that then replaces the history file, so that the history doesn't
+// need to fit in memory.
+fn compact(path: &Path, retention: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(Box::new(e)),
+    };
+    debug!("Compacting history...");
+    let since = Utc::now() - chrono::Duration::from_std(retention)?;
+
+    let tmp_path = path.with_extension("jsonl.tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        match serde_json::from_str::<Record>(&line) {
+            Ok(record) => {
+                if record.time >= since {
+                    writer.write_all(line.as_bytes())?;
+                    writer.write_all(b"\n")?;
+                }
+            }
+            Err(e) => warn!("Dropping invalid history record at line {}: {}", i + 1, e),
+        }
+    }
+    writer.flush()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// A continuous period during which a device was connected.
+pub struct Session {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Link speed at each poll.
+    pub speeds: Vec<(DateTime<Utc>, usize)>,
+    /// Latest known state of the device.
+    pub client: ClientInfo,
+    /// Whether the device was seen arriving, i.e. it was absent from the previous device table.
+    pub arrival_observed: bool,
+    /// Whether the device was seen leaving, i.e. it was absent from the next device table.
+    pub departure_observed: bool,
+}
+
+impl Session {
+    pub fn duration(&self) -> chrono::Duration {
+        self.end - self.start
+    }
+}
+
+/// Computes the sessions of a device from the recorded device tables. A session ends when the
+/// device is missing from a device table, or when no device table was recorded for more than
+/// `max_gap` (i.e. monitoring was interrupted).
+pub fn sessions(records: &[Record], mac: &MacAddr, max_gap: chrono::Duration) -> Vec<Session> {
+    let mut sessions = Vec::new();
+    let mut current: Option<Session> = None;
+    let mut last_time: Option<DateTime<Utc>> = None;
+
+    for record in records {
+        let table = match &record.data {
+            RecordData::Devices(table) => table,
+            _ => continue,
+        };
+
+        let interrupted = last_time.map_or(false, |last_time| record.time - last_time > max_gap);
+        if interrupted {
+            sessions.extend(current.take());
+        }
+        let monitored = last_time.is_some() && !interrupted;
+        last_time = Some(record.time);
+
+        let client = table
+            .wifi
+            .clientinfo
+            .iter()
+            .find(|client| client.mac == *mac);
+        match (client, current.as_mut()) {
+            (Some(client), Some(session)) => {
+                session.end = record.time;
+                session.speeds.push((record.time, client.speed));
+                session.client = client.clone();
+            }
+            (Some(client), None) => {
+                current = Some(Session {
+                    start: record.time,
+                    end: record.time,
+                    speeds: vec![(record.time, client.speed)],
+                    client: client.clone(),
+                    arrival_observed: monitored,
+                    departure_observed: false,
+                });
+            }
+            (None, Some(session)) => {
+                session.departure_observed = true;
+                sessions.extend(current.take());
+            }
+            (None, None) => (),
+        }
+    }
+    sessions.extend(current);
+    sessions
+}
+
+/// Prints the connection intervals and link speed of a device.
+pub fn print_device_history(
+    records: &[Record],
+    mac: &MacAddr,
+    since: DateTime<Utc>,
+    max_gap: chrono::Duration,
+) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    write_device_history(&mut stdout, records, mac, since, max_gap)
+}
+
+// Periods during which the device may have connected or disconnected without being seen, because
+// monitoring wasn't running, are reported as such rather than as disconnections.
+fn write_device_history(
+    out: &mut impl Write,
+    records: &[Record],
+    mac: &MacAddr,
+    since: DateTime<Utc>,
+    max_gap: chrono::Duration,
+) -> io::Result<()> {
+    let sessions = sessions(records, mac, max_gap);
+    writeln!(out, "Device {} since {}:", mac, format_time(since))?;
+    if sessions.is_empty() {
+        writeln!(out, "  never connected")?;
+        return Ok(());
+    }
+
+    let mut last: Option<&Session> = None;
+    for (i, session) in sessions.iter().enumerate() {
+        if let Some(last) = last {
+            let gap = if !last.departure_observed || !session.arrival_observed {
+                "not monitored"
+            } else {
+                "disconnected "
+            };
+            writeln!(
+                out,
+                "  {} {} -> {} ({})",
+                gap,
+                format_time(last.end),
+                format_time(session.start),
+                format_duration(session.start - last.end)
+            )?;
+        }
+        write!(
+            out,
+            "  connected     {} -> {} ({}) as {:?}",
+            format_time(session.start),
+            format_time(session.end),
+            format_duration(session.duration()),
+            session.client.hostname
+        )?;
+        // The first and last sessions may extend beyond the records.
+        if i == 0 && !session.arrival_observed {
+            write!(out, ", arrival not monitored")?;
+        }
+        if i == sessions.len() - 1 && !session.departure_observed {
+            write!(out, ", departure not monitored")?;
+        }
+        writeln!(out)?;
+        last = Some(session);
+    }
+
+    writeln!(out, "Speed:")?;
+    let mut last_speed = None;
+    for session in &sessions {
+        for &(time, speed) in &session.speeds {
+            if last_speed != Some(speed) {
+                writeln!(out, "  {} {:5}", format_time(time), speed)?;
+                last_speed = Some(speed);
+            }
+        }
+        last_speed = None;
+    }
+    Ok(())
+}
+
+pub fn format_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let seconds = duration.num_seconds().max(0) as u64;
+    humantime::format_duration(Duration::from_secs(seconds)).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channels() -> RecordData {
+        RecordData::Channels(DocsisChannels {
+            downstream: Vec::new(),
+            upstream: Vec::new(),
+        })
+    }
+
+    fn line(time: DateTime<Utc>, data: RecordData) -> String {
+        serde_json::to_string(&Record { time, data }).unwrap()
+    }
+
+    #[test]
+    fn compaction_prunes_old_and_invalid_records() {
+        let dir = std::env::temp_dir().join(format!("connect-box-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.jsonl");
+        let now = Utc::now();
+        let old = line(now - chrono::Duration::days(2), channels());
+        let recent = line(now - chrono::Duration::hours(1), channels());
+        fs::write(&path, format!("{}\n{{corrupted\n{}\n", old, recent)).unwrap();
+
+        compact(&path, Duration::from_secs(24 * 3600)).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let records = read(&path, now - chrono::Duration::days(7)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(content, format!("{}\n", recent));
+        assert_eq!(records.len(), 1);
+        assert!(matches!(records[0].data, RecordData::Channels(_)));
+    }
+
+    fn devices(time: DateTime<Utc>, clients: &[&ClientInfo]) -> Record {
+        let clients: Vec<ClientInfo> = clients.iter().map(|&client| client.clone()).collect();
+        Record {
+            time,
+            data: RecordData::Devices(LanUserTable {
+                customer: String::new(),
+                total_client: clients.len(),
+                wifi: crate::types::Wifi {
+                    clientinfo: clients,
+                },
+            }),
+        }
+    }
+
+    #[test]
+    fn device_history_with_monitoring_gap() {
+        let tv = ClientInfo {
+            mac: "00:11:22:33:44:55".parse().unwrap(),
+            hostname: "tv".to_owned(),
+            index: 0,
+            ipv4: None,
+            ipv6: None,
+            interface: "Ethernet 1".to_owned(),
+            interfaceid: 1,
+            method: crate::types::AddressMethod::Dhcp,
+            lease_time: Duration::from_secs(3600),
+            speed: 100,
+        };
+        let start = Utc::now() - chrono::Duration::hours(12);
+        let at = |minutes| start + chrono::Duration::minutes(minutes);
+        let records = [
+            // Already connected when monitoring starts.
+            devices(at(0), &[&tv]),
+            devices(at(5), &[&tv]),
+            // Monitoring stops for the night.
+            devices(at(600), &[&tv]),
+            // The TV drops off, and comes back.
+            devices(at(605), &[]),
+            devices(at(610), &[&tv]),
+        ];
+
+        let mut output = Vec::new();
+        write_device_history(&mut output, &records, &tv.mac, start, MAX_GAP).unwrap();
+        let expected = format!(
+            "Device 00:11:22:33:44:55 since {}:
+  connected     {} -> {} (5m) as \"tv\", arrival not monitored
+  not monitored {} -> {} (9h 55m)
+  connected     {} -> {} (0s) as \"tv\"
+  disconnected  {} -> {} (10m)
+  connected     {} -> {} (0s) as \"tv\", departure not monitored
+Speed:
+  {}   100
+  {}   100
+  {}   100
+",
+            format_time(start),
+            format_time(at(0)),
+            format_time(at(5)),
+            format_time(at(5)),
+            format_time(at(600)),
+            format_time(at(600)),
+            format_time(at(600)),
+            format_time(at(600)),
+            format_time(at(610)),
+            format_time(at(610)),
+            format_time(at(610)),
+            format_time(at(0)),
+            format_time(at(600)),
+            format_time(at(610)),
+        );
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn compaction_of_missing_file() {
+        let path = std::env::temp_dir().join("connect-box-missing-history.jsonl");
+        compact(&path, Duration::from_secs(3600)).unwrap();
+        assert!(!path.exists());
+    }
+}
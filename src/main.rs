@@ -1,45 +1,257 @@
+mod alert;
+mod alias;
+mod api;
 mod cli;
+mod columns;
+mod commands;
+mod config;
 mod connect_box;
+mod correlation;
 mod demo;
+mod docsis;
+mod history;
+mod monitor;
+mod password;
+mod report;
 mod router;
+mod session;
+mod temperature;
+mod terminal;
+mod text;
+mod theme;
 mod tui;
 mod types;
+mod vendor;
+mod web;
 
+use alert::{Alerter, CommandNotifier, Notifier, SyslogNotifier, WebhookNotifier};
+use alias::Aliases;
+use columns::Column;
+use commands::Command;
 use connect_box::ConnectBox;
 use demo::DemoRouter;
-use futures::future::FutureExt;
+use futures::future::{self, Future, FutureExt};
 use futures::select;
 use futures::stream;
 use futures::stream::StreamExt;
+use history::HistoryStore;
 use log::{debug, info};
+use monitor::Monitor;
 use router::Router;
+use std::collections::VecDeque;
+use std::ffi::OsString;
 use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use temperature::TemperatureMonitor;
 use tokio::{signal, time};
-use tui::Tui;
-use types::LanUserTableDiff;
+use tui::{Action, Tui};
+use types::{LanUserTable, LanUserTableDiff};
+use zeroize::Zeroizing;
+
+// Options shared by the monitoring loops.
+struct Options {
+    refresh_duration: time::Duration,
+    temperature_refresh_duration: time::Duration,
+    status_refresh_duration: time::Duration,
+    tui: bool,
+    aliases_path: PathBuf,
+    columns: Vec<Column>,
+    linger: time::Duration,
+    joined_highlight: time::Duration,
+    theme: theme::Theme,
+    // Set to serve the web dashboard instead of printing the devices or showing the TUI.
+    web: Option<web::Config>,
+}
+
+// Period at which key presses are handled in the TUI.
+const INPUT_PERIOD: time::Duration = time::Duration::from_millis(50);
+
+// Events of the monitoring loops.
+#[derive(Clone, Copy)]
+enum Tick {
+    Devices,
+    Temperature,
+    Status,
+    Input,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Before the runtime starts its threads.
+    let env_password = password::take_env();
+    run(env_password)
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn run(env_password: Option<Zeroizing<String>>) -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
 
     // CLI.
     let mut app = cli::build_cli();
 
-    let matches = match app.get_matches_from_safe_borrow(std::env::args_os()) {
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    let matches = match app.get_matches_from_safe_borrow(&args) {
         Ok(matches) => matches,
         Err(error) => error.exit(),
     };
 
+    // Configuration file.
+    let config_path = matches
+        .value_of_os("config")
+        .map(PathBuf::from)
+        .unwrap_or_else(config::default_path);
+    if let ("config", Some(matches)) = matches.subcommand() {
+        if let ("check", Some(_)) = matches.subcommand() {
+            match config::check(&config_path) {
+                Ok(count) => println!(
+                    "The {} profiles of {} are valid",
+                    count,
+                    config_path.display()
+                ),
+                Err(errors) => {
+                    for error in errors {
+                        eprintln!("{}", error);
+                    }
+                    std::process::exit(1);
+                }
+            }
+        }
+        return Ok(());
+    }
+    // The settings of the profile are added around the command-line arguments, which may end with
+    // a subcommand.
+    let profile_args = config::profile_args(&config_path, matches.value_of("profile"), &matches)?;
+    let matches = if profile_args.is_empty() {
+        matches
+    } else {
+        profile_args.insert_into(&mut args);
+        match cli::build_cli().get_matches_from_safe(&args) {
+            Ok(matches) => matches,
+            Err(error) => error.exit(),
+        }
+    };
+
+    let history_path = matches
+        .value_of_os("history-file")
+        .map(PathBuf::from)
+        .unwrap_or_else(history::default_path);
+
+    match matches.subcommand() {
+        ("history", Some(matches)) => {
+            let mac = matches.value_of("device").unwrap().parse().unwrap();
+            let since = cli::parse_duration(matches.value_of("since").unwrap()).unwrap();
+            let since = chrono::Utc::now() - chrono::Duration::from_std(since)?;
+            let records = history::read(&history_path, since)?;
+            history::print_device_history(&records, &mac, since, history::MAX_GAP)?;
+            return Ok(());
+        }
+        ("report", Some(matches)) => {
+            let period = match matches.value_of("period").unwrap() {
+                "day" => report::Period::Day,
+                "week" => report::Period::Week,
+                _ => unreachable!(),
+            };
+            let since = report::period_start(period, chrono::Local::now());
+            let records = history::read(&history_path, since)?;
+            let reports = report::build(&records, history::MAX_GAP);
+            match matches.value_of("format").unwrap() {
+                "table" => report::print_table(&reports),
+                "json" => report::print_json(&reports)?,
+                "csv" => report::print_csv(&reports)?,
+                _ => unreachable!(),
+            }
+            return Ok(());
+        }
+        _ => (),
+    }
+
+    let command = match matches.subcommand() {
+        ("block", Some(matches)) => Some(Command::Block(
+            matches.value_of("device").unwrap().parse().unwrap(),
+        )),
+        ("reboot", Some(_)) => Some(Command::Reboot),
+        ("export", Some(matches)) => Some(Command::Export(
+            matches.value_of("format").unwrap().parse().unwrap(),
+            matches.value_of_os("output").map(PathBuf::from),
+        )),
+        _ => None,
+    };
+
     let demo = matches.is_present("demo");
-    let tui = matches.is_present("tui");
+    if !demo && !matches.is_present("host") {
+        clap::Error::with_description(
+            "The --host argument is required to connect to the router",
+            clap::ErrorKind::MissingRequiredArgument,
+        )
+        .exit();
+    }
     let refresh: u64 = matches.value_of("refresh").unwrap().parse().unwrap();
+    let temperature_refresh: u64 = matches
+        .value_of("temperature-refresh")
+        .unwrap()
+        .parse()
+        .unwrap();
+    let status_refresh: u64 = matches.value_of("status-refresh").unwrap().parse().unwrap();
+    let columns = match matches.value_of("columns") {
+        Some(columns) => columns::parse_list(columns).unwrap(),
+        None => {
+            columns::load(&columns::default_path())?.unwrap_or_else(|| Column::DEFAULT.to_vec())
+        }
+    };
+    let options = Options {
+        refresh_duration: time::Duration::from_secs(refresh),
+        temperature_refresh_duration: time::Duration::from_secs(temperature_refresh),
+        status_refresh_duration: time::Duration::from_secs(status_refresh),
+        tui: matches.is_present("tui"),
+        aliases_path: matches
+            .value_of_os("aliases-file")
+            .map(PathBuf::from)
+            .unwrap_or_else(alias::default_path),
+        columns,
+        linger: cli::parse_duration(matches.value_of("linger").unwrap()).unwrap(),
+        joined_highlight: cli::parse_duration(matches.value_of("joined-highlight").unwrap())
+            .unwrap(),
+        theme: matches.value_of("theme").unwrap().parse().unwrap(),
+        web: match matches.subcommand() {
+            ("serve", Some(matches)) => Some(web::Config {
+                listen: matches.value_of("listen").unwrap().parse().unwrap(),
+                basic_auth: matches.value_of("basic-auth").map(str::to_owned),
+            }),
+            _ => None,
+        },
+    };
+
+    let alerter = if matches.is_present("alert") {
+        Some(build_alerter(&matches)?)
+    } else {
+        None
+    };
+    let temperature = TemperatureMonitor::new(
+        matches
+            .value_of("max-temperature")
+            .map(|t| t.parse().unwrap()),
+        matches
+            .value_of("max-tuner-temperature")
+            .map(|t| t.parse().unwrap()),
+    );
+    let history = if matches.is_present("record") {
+        let retention = cli::parse_duration(matches.value_of("retention").unwrap()).unwrap();
+        Some(HistoryStore::open(&history_path, retention)?)
+    } else {
+        None
+    };
+    let monitor = Monitor::new(
+        matches.is_present("correlate-macs"),
+        alerter,
+        temperature,
+        history,
+    );
 
     if demo {
         let mut router = DemoRouter::new();
-        launch_with_router(&mut router, time::Duration::from_secs(refresh), tui).await
+        launch_with_router(&mut router, &options, monitor, command).await
     } else {
         let host = matches.value_of("host").unwrap();
-        let password = matches.value_of("password").unwrap();
+        let password = password::read(password_source(&matches, host, env_password))?;
         let timeout: u64 = matches.value_of("timeout").unwrap().parse().unwrap();
         let throttle: u64 = matches.value_of("throttle").unwrap().parse().unwrap();
 
@@ -52,21 +264,80 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             time::Duration::from_secs(throttle),
         )
         .await?;
-        launch_with_router(&mut router, time::Duration::from_secs(refresh), tui).await
+        launch_with_router(&mut router, &options, monitor, command).await
     }
 }
 
+fn password_source(
+    matches: &clap::ArgMatches,
+    host: &str,
+    env_password: Option<Zeroizing<String>>,
+) -> password::Source {
+    if let Some(password) = matches.value_of("password") {
+        eprintln!(
+            "Warning: --password is deprecated, as the password shows up in the shell history and \
+             the list of processes. Use --password-file, --password-keyring or the {} environment \
+             variable instead.",
+            password::ENV_VAR
+        );
+        password::Source::Arg(password.to_owned().into())
+    } else if let Some(path) = matches.value_of_os("password-file") {
+        password::Source::File(PathBuf::from(path))
+    } else if matches.is_present("password-keyring") {
+        password::Source::Keyring(host.to_owned())
+    } else if let Some(password) = env_password {
+        password::Source::Env(password)
+    } else {
+        password::Source::Prompt
+    }
+}
+
+fn build_alerter(matches: &clap::ArgMatches) -> Result<Alerter, Box<dyn std::error::Error>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+    if let Some(command) = matches.value_of("alert-command") {
+        notifiers.push(Box::new(CommandNotifier::new(command.to_owned())));
+    }
+    if let Some(url) = matches.value_of("alert-webhook") {
+        notifiers.push(Box::new(WebhookNotifier::new(url.to_owned())?));
+    }
+    if matches.is_present("alert-syslog") {
+        notifiers.push(Box::new(SyslogNotifier::new()?));
+    }
+    #[cfg(feature = "desktop-notifications")]
+    if matches.is_present("alert-desktop") {
+        notifiers.push(Box::new(alert::DesktopNotifier::new()));
+    }
+
+    let known = matches.values_of("known").into_iter().flatten();
+    let tagged = matches.values_of("tag").into_iter().flatten();
+    Ok(Alerter::new(
+        known.map(|mac| mac.parse().unwrap()),
+        tagged.map(|mac| mac.parse().unwrap()),
+        notifiers,
+    ))
+}
+
 async fn launch_with_router<R: Router>(
     router: &mut R,
-    refresh_duration: time::Duration,
-    tui: bool,
+    options: &Options,
+    monitor: Monitor,
+    command: Option<Command>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    select!(
-        res = main_loop(router, refresh_duration, tui).fuse() => res,
-        res = wait_interrupt().fuse() => res,
-    )?;
+    if let Some(command) = command {
+        let summary = commands::run(router, &command).await?;
+        eprintln!("{}", summary);
+        // Rebooting ends the session.
+        if let Command::Reboot = command {
+            return Ok(());
+        }
+    } else {
+        select!(
+            res = main_loop(router, options, monitor).fuse() => res,
+            res = wait_interrupt().fuse() => res,
+        )?;
 
-    info!("Selected");
+        info!("Selected");
+    }
 
     router.logout().await?;
     Ok(())
@@ -80,54 +351,302 @@ async fn wait_interrupt() -> Result<(), Box<dyn std::error::Error>> {
 
 async fn main_loop<R: Router>(
     router: &mut R,
-    refresh_duration: time::Duration,
-    tui: bool,
+    options: &Options,
+    monitor: Monitor,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if tui {
-        tui_loop(router, refresh_duration).await
+    if let Some(config) = &options.web {
+        serve_loop(router, options, config, monitor).await
+    } else if options.tui {
+        tui_loop(router, options, monitor).await
     } else {
-        diff_loop(router, refresh_duration).await
+        diff_loop(router, options, monitor).await
     }
 }
 
+// Stream of ticks to refresh the devices and the temperature, each on their own schedule.
+fn ticks(options: &Options) -> impl futures::Stream<Item = Tick> {
+    stream::select(
+        time::throttle(options.refresh_duration, stream::repeat(Tick::Devices)),
+        time::throttle(
+            options.temperature_refresh_duration,
+            stream::repeat(Tick::Temperature),
+        ),
+    )
+}
+
 async fn diff_loop<R: Router>(
     router: &mut R,
-    refresh_duration: time::Duration,
+    options: &Options,
+    mut monitor: Monitor,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut devices = router.devices().await?;
     println!("Devices: {:#?}", devices);
-    let temperature = router.temperature().await?;
-    println!("Temperature: {:#?}", temperature);
+    monitor.update_devices(&devices).await;
 
-    let mut throttle = time::throttle(refresh_duration, stream::repeat(()));
-    loop {
-        debug!("Throttling...");
-        throttle.next().await;
-        debug!("Querying for devices...");
-        let newdevices = router.devices().await?;
-        println!(
-            "Devices: {:#?}",
-            LanUserTableDiff {
-                old: &devices,
-                new: &newdevices
+    // The channels are only polled to be recorded.
+    let recording = monitor.is_recording();
+    let mut ticks = stream::select(
+        ticks(options),
+        time::throttle(
+            options.status_refresh_duration,
+            stream::repeat(Tick::Status),
+        )
+        .filter(move |_| future::ready(recording)),
+    );
+    while let Some(tick) = ticks.next().await {
+        match tick {
+            Tick::Devices => {
+                debug!("Querying for devices...");
+                let newdevices = router.devices().await?;
+                println!(
+                    "Devices: {:#?}",
+                    LanUserTableDiff {
+                        old: &devices,
+                        new: &newdevices,
+                        departed: monitor.departed(),
+                    }
+                );
+                monitor.update_devices(&newdevices).await;
+                devices = newdevices;
             }
-        );
-        devices = newdevices;
+            Tick::Temperature => {
+                debug!("Querying for temperature...");
+                let temperature = router.temperature().await?;
+                if monitor.temperature_history().latest() != Some(&temperature) {
+                    println!("Temperature: {:#?}", temperature);
+                }
+                monitor.update_temperature(temperature).await;
+            }
+            Tick::Status => {
+                debug!("Querying for channels...");
+                let channels = router.channels().await?;
+                monitor.update_channels(&channels);
+            }
+            // The input is only used in the TUI.
+            Tick::Input => (),
+        }
     }
+    Ok(())
+}
+
+async fn serve_loop<R: Router>(
+    router: &mut R,
+    options: &Options,
+    config: &web::Config,
+    monitor: Monitor,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // The dashboard and the calls of the API share the router session.
+    let (shared, session) = session::shared();
+    let mut dashboard_router = shared.clone();
+    let dashboard = web::Dashboard::new();
+    select!(
+        () = session.run(router).fuse() => Ok(()),
+        res = web::serve(config, dashboard.clone(), shared).fuse() => res,
+        res = dashboard_loop(&mut dashboard_router, options, monitor, &dashboard).fuse() => res,
+    )
+}
+
+// Keeps the web dashboard up to date, like the TUI.
+async fn dashboard_loop<R: Router>(
+    router: &mut R,
+    options: &Options,
+    mut monitor: Monitor,
+    dashboard: &web::Dashboard,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut ticks = stream::select(
+        ticks(options),
+        time::throttle(
+            options.status_refresh_duration,
+            stream::repeat(Tick::Status),
+        ),
+    );
+    while let Some(tick) = ticks.next().await {
+        match tick {
+            Tick::Devices => {
+                debug!("Querying for devices...");
+                let devices = router.devices().await?;
+                dashboard.update_devices(devices.clone(), monitor.departed());
+                monitor.update_devices(&devices).await;
+            }
+            Tick::Temperature => {
+                debug!("Querying for temperature...");
+                let temperature = router.temperature().await?;
+                dashboard.update_temperature(&temperature);
+                monitor.update_temperature(temperature).await;
+            }
+            Tick::Status => {
+                debug!("Querying for router status...");
+                let settings = router.global_settings().await?;
+                let system = router.system_info().await?;
+                let channels = router.channels().await?;
+                monitor.update_channels(&channels);
+                dashboard.update_status(settings, system, channels);
+            }
+            Tick::Input => (),
+        }
+    }
+    Ok(())
 }
 
 async fn tui_loop<R: Router>(
     router: &mut R,
-    refresh_duration: time::Duration,
+    options: &Options,
+    mut monitor: Monitor,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut tui = Tui::new();
+    let aliases = Aliases::load(&options.aliases_path)?;
+    let mut tui = Tui::new(
+        aliases,
+        options.columns.clone(),
+        options.linger,
+        options.joined_highlight,
+        options.theme,
+    );
+    // Actions requested while waiting for the router, run once it is available.
+    let mut deferred = VecDeque::new();
 
-    let mut throttle = time::throttle(refresh_duration, stream::repeat(()));
+    // The devices are refreshed on their own stream, so that their refresh period can be changed
+    // from the command palette.
+    let device_ticks = |period| time::throttle(period, stream::repeat(Tick::Devices));
+    let mut devices = device_ticks(options.refresh_duration);
+    let mut ticks = stream::select(
+        stream::select(
+            time::throttle(
+                options.temperature_refresh_duration,
+                stream::repeat(Tick::Temperature),
+            ),
+            time::throttle(
+                options.status_refresh_duration,
+                stream::repeat(Tick::Status),
+            ),
+        ),
+        time::throttle(INPUT_PERIOD, stream::repeat(Tick::Input)),
+    );
     loop {
-        debug!("Throttling...");
-        throttle.next().await;
-        debug!("Querying for devices...");
-        let devices = router.devices().await?;
-        tui.update(devices);
+        let action = match deferred.pop_front() {
+            Some(action) => action,
+            None => {
+                let tick = select!(
+                    tick = devices.next().fuse() => tick,
+                    tick = ticks.next().fuse() => tick,
+                );
+                let tick = match tick {
+                    Some(tick) => tick,
+                    None => break,
+                };
+                match tick {
+                    Tick::Devices => {
+                        let request = refresh_devices(router, &mut monitor);
+                        match with_input(&mut tui, &mut deferred, request).await {
+                            Some(devices) => tui.update(devices?),
+                            None => break,
+                        }
+                    }
+                    Tick::Temperature => {
+                        let request = async {
+                            debug!("Querying for temperature...");
+                            let temperature = router.temperature().await?;
+                            monitor.update_temperature(temperature).await;
+                            Ok::<_, Box<dyn std::error::Error>>(())
+                        };
+                        match with_input(&mut tui, &mut deferred, request).await {
+                            Some(result) => result?,
+                            None => break,
+                        }
+                        tui.update_temperature(monitor.temperature_history());
+                    }
+                    Tick::Status => {
+                        let request = async {
+                            debug!("Querying for router status...");
+                            let settings = router.global_settings().await?;
+                            let system = router.system_info().await?;
+                            let channels = router.channels().await?;
+                            monitor.update_channels(&channels);
+                            Ok::<_, Box<dyn std::error::Error>>((settings, system, channels))
+                        };
+                        match with_input(&mut tui, &mut deferred, request).await {
+                            Some(result) => {
+                                let (settings, system, channels) = result?;
+                                tui.update_modem(settings, system, channels);
+                            }
+                            None => break,
+                        }
+                    }
+                    Tick::Input => {
+                        if let Some(action) = tui.poll_input() {
+                            deferred.push_back(action);
+                        }
+                    }
+                }
+                continue;
+            }
+        };
+
+        let command = match action {
+            Action::Quit => break,
+            Action::RefreshDevices => {
+                let request = refresh_devices(router, &mut monitor);
+                match with_input(&mut tui, &mut deferred, request).await {
+                    Some(devices) => tui.update(devices?),
+                    None => break,
+                }
+                continue;
+            }
+            Action::Run(Command::Refresh(period)) => {
+                devices = device_ticks(period);
+                tui.show_message(format!(
+                    "Refreshing the devices every {}",
+                    humantime::format_duration(period)
+                ));
+                continue;
+            }
+            Action::Run(Command::Export(format, None)) => {
+                // The standard output is taken by the TUI.
+                let path = PathBuf::from(format!(
+                    "connect-box-devices-{}.{}",
+                    chrono::Local::now().format("%Y%m%d-%H%M%S"),
+                    format.extension()
+                ));
+                Command::Export(format, Some(path))
+            }
+            Action::Run(command) => command,
+        };
+        match with_input(&mut tui, &mut deferred, commands::run(router, &command)).await {
+            Some(Ok(summary)) => tui.show_message(summary),
+            Some(Err(e)) => tui.show_message(format!("Command failed: {}", e)),
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+async fn refresh_devices<R: Router>(
+    router: &mut R,
+    monitor: &mut Monitor,
+) -> Result<LanUserTable, Box<dyn std::error::Error>> {
+    debug!("Querying for devices...");
+    let devices = router.devices().await?;
+    monitor.update_devices(&devices).await;
+    Ok(devices)
+}
+
+// Waits for a request to the router, which can take several seconds, while keeping the TUI
+// responsive to keys and resizes. The actions that need the router are deferred until it is
+// available, and quitting cancels the request, in which case `None` is returned.
+async fn with_input<T>(
+    tui: &mut Tui,
+    deferred: &mut VecDeque<Action>,
+    request: impl Future<Output = T>,
+) -> Option<T> {
+    let mut request = Box::pin(request.fuse());
+    let mut input = time::interval(INPUT_PERIOD);
+    loop {
+        select!(
+            result = request => return Some(result),
+            _ = input.tick().fuse() => match tui.poll_input() {
+                Some(Action::Quit) => return None,
+                Some(action) => deferred.push_back(action),
+                None => (),
+            },
+        );
     }
 }
@@ -0,0 +1,101 @@
+use crate::alert::{Alert, Alerter};
+use crate::correlation::Correlator;
+use crate::history::{HistoryStore, RecordData};
+use crate::temperature::{TemperatureHistory, TemperatureMonitor};
+use crate::types::{ClientInfo, CmState, DocsisChannels, LanUserTable};
+use log::warn;
+
+/// Processing common to all the monitoring loops: correlation of randomized MAC addresses,
+/// alerts, temperature thresholds and recording of the history.
+pub struct Monitor {
+    devices: Option<LanUserTable>,
+    correlator: Option<Correlator>,
+    alerter: Option<Alerter>,
+    temperature: TemperatureMonitor,
+    history: Option<HistoryStore>,
+}
+
+impl Monitor {
+    pub fn new(
+        correlate: bool,
+        alerter: Option<Alerter>,
+        temperature: TemperatureMonitor,
+        history: Option<HistoryStore>,
+    ) -> Self {
+        Self {
+            devices: None,
+            correlator: if correlate {
+                Some(Correlator::new())
+            } else {
+                None
+            },
+            alerter,
+            temperature,
+            history,
+        }
+    }
+
+    /// Departed devices to match randomized MAC addresses against, if MAC correlation is enabled.
+    pub fn departed(&self) -> Option<&[ClientInfo]> {
+        self.correlator.as_ref().map(|c| c.departed())
+    }
+
+    /// Whether the polled data is recorded in the history.
+    pub fn is_recording(&self) -> bool {
+        self.history.is_some()
+    }
+
+    pub fn temperature_history(&self) -> &TemperatureHistory {
+        self.temperature.history()
+    }
+
+    pub async fn update_devices(&mut self, newdevices: &LanUserTable) {
+        self.record(RecordData::Devices(newdevices.clone()));
+
+        match &self.devices {
+            Some(devices) => {
+                if let Some(alerter) = self.alerter.as_mut() {
+                    let departed = self.correlator.as_ref().map(|c| c.departed());
+                    alerter.update(devices, newdevices, departed).await;
+                }
+                if let Some(correlator) = self.correlator.as_mut() {
+                    correlator.update(&devices.wifi.clientinfo, &newdevices.wifi.clientinfo);
+                }
+            }
+            None => {
+                if let Some(alerter) = self.alerter.as_mut() {
+                    alerter.start(newdevices);
+                }
+            }
+        }
+        self.devices = Some(newdevices.clone());
+    }
+
+    pub async fn update_temperature(&mut self, state: CmState) {
+        self.record(RecordData::Temperature(state.clone()));
+
+        for alert in self.temperature.update(state) {
+            self.raise(alert).await;
+        }
+    }
+
+    pub fn update_channels(&mut self, channels: &DocsisChannels) {
+        self.record(RecordData::Channels(channels.clone()));
+    }
+
+    // Dispatches an alert, or only logs it if alerts aren't enabled.
+    async fn raise(&mut self, alert: Alert) {
+        match self.alerter.as_mut() {
+            Some(alerter) => alerter.fire(&alert).await,
+            None => warn!("{}", alert),
+        }
+    }
+
+    fn record(&mut self, data: RecordData) {
+        if let Some(history) = self.history.as_mut() {
+            if let Err(e) = history.append(data) {
+                warn!("Failed to record history: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Sources of the router password, which is kept in memory that is zeroed once dropped.
+
+use std::io;
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+
+/// Environment variable from which the password is read, if no other source is given.
+pub const ENV_VAR: &str = "CONNECT_BOX_PASSWORD";
+
+// Service under which the password is stored in the keyring, with the host as username.
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "connect-box";
+
+/// Where to read the password from.
+pub enum Source {
+    /// Given on the command line, where it shows up in the shell history and the list of processes.
+    Arg(Zeroizing<String>),
+    File(PathBuf),
+    /// The Secret Service keyring, for a host.
+    Keyring(String),
+    /// The environment variable, taken at startup.
+    Env(Zeroizing<String>),
+    /// A prompt on the terminal.
+    Prompt,
+}
+
+/// Takes the password out of the environment variable, so that the commands run for alerts don't
+/// get it. This must be called before any thread is started, since modifying the environment isn't
+/// thread-safe.
+pub fn take_env() -> Option<Zeroizing<String>> {
+    let password = std::env::var(ENV_VAR).ok()?;
+    std::env::remove_var(ENV_VAR);
+    Some(Zeroizing::new(password))
+}
+
+/// Reads the password from its source.
+pub fn read(source: Source) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    match source {
+        Source::Arg(password) => Ok(password),
+        Source::File(path) => {
+            let mut password = Zeroizing::new(std::fs::read_to_string(&path).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("Failed to read the password from {}: {}", path.display(), e),
+                )
+            })?);
+            // Files usually end with a newline, which isn't part of the password.
+            let len = password.trim_end_matches(&['\r', '\n'][..]).len();
+            password.truncate(len);
+            Ok(password)
+        }
+        Source::Keyring(host) => read_keyring(&host),
+        Source::Env(password) => Ok(password),
+        Source::Prompt => {
+            let password =
+                rpassword::read_password_from_tty(Some("Router password: ")).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("No password given, and failed to prompt for it: {}", e),
+                    )
+                })?;
+            Ok(Zeroizing::new(password))
+        }
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn read_keyring(host: &str) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    let password = keyring::Entry::new(KEYRING_SERVICE, host)
+        .and_then(|entry| entry.get_password())
+        .map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Failed to read the password of {} from the keyring: {}",
+                    host, e
+                ),
+            )
+        })?;
+    Ok(Zeroizing::new(password))
+}
+
+#[cfg(not(feature = "keyring"))]
+fn read_keyring(_host: &str) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "connect-box was built without the keyring feature",
+    )
+    .into())
+}
@@ -0,0 +1,263 @@
+use crate::history::{self, Record, RecordData, Session};
+use crate::text;
+use crate::types::MacAddr;
+use chrono::{DateTime, Datelike, Local, TimeZone, Timelike, Utc};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+/// Calendar period covered by a report.
+#[derive(Debug, Clone, Copy)]
+pub enum Period {
+    Day,
+    /// From Monday.
+    Week,
+}
+
+/// Start of the current period, at local midnight.
+pub fn period_start(period: Period, now: DateTime<Local>) -> DateTime<Utc> {
+    let days = match period {
+        Period::Day => 0,
+        Period::Week => now.weekday().num_days_from_monday(),
+    };
+    let date = now.naive_local().date() - chrono::Duration::days(days.into());
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+    // Midnight may be skipped by a daylight saving time change, in which case the day starts an
+    // hour later.
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .or_else(|| {
+            Local
+                .from_local_datetime(&(midnight + chrono::Duration::hours(1)))
+                .earliest()
+        })
+        .map_or_else(
+            || now.with_timezone(&Utc),
+            |start| start.with_timezone(&Utc),
+        )
+}
+
+/// Presence statistics of a device over the reporting period.
+#[derive(Debug, Serialize)]
+pub struct DeviceReport {
+    pub mac: MacAddr,
+    pub hostname: String,
+    /// Total connected time, in seconds.
+    pub connected_time: i64,
+    pub sessions: usize,
+    /// Longest period without connection between two sessions, in seconds.
+    pub longest_offline_gap: Option<i64>,
+    /// Median local time of the day at which the device arrives, as "HH:MM". Times of day wrap
+    /// around midnight, so that e.g. the median of 23:30, 00:10 and 00:30 is 00:10.
+    pub typical_arrival: Option<String>,
+    /// Median local time of the day at which the device leaves, as "HH:MM".
+    pub typical_departure: Option<String>,
+    /// Average link speed.
+    pub average_speed: Option<f64>,
+}
+
+/// Computes the presence report of each device seen in the records.
+pub fn build(records: &[Record], max_gap: chrono::Duration) -> Vec<DeviceReport> {
+    let macs: BTreeSet<MacAddr> = records
+        .iter()
+        .filter_map(|record| match &record.data {
+            RecordData::Devices(table) => Some(table),
+            _ => None,
+        })
+        .flat_map(|table| table.wifi.clientinfo.iter())
+        .map(|client| client.mac)
+        .collect();
+
+    macs.into_iter()
+        .map(|mac| {
+            let sessions = history::sessions(records, &mac, max_gap);
+            device_report(mac, &sessions)
+        })
+        .collect()
+}
+
+fn device_report(mac: MacAddr, sessions: &[Session]) -> DeviceReport {
+    let connected_time = sessions.iter().map(|s| s.duration().num_seconds()).sum();
+    let longest_offline_gap = sessions
+        .windows(2)
+        .map(|pair| (pair[1].start - pair[0].end).num_seconds())
+        .max();
+    let typical_arrival = median_time_of_day(
+        sessions
+            .iter()
+            .filter(|s| s.arrival_observed)
+            .map(|s| s.start),
+    );
+    let typical_departure = median_time_of_day(
+        sessions
+            .iter()
+            .filter(|s| s.departure_observed)
+            .map(|s| s.end),
+    );
+
+    let speeds: Vec<usize> = sessions
+        .iter()
+        .flat_map(|s| s.speeds.iter().map(|&(_, speed)| speed))
+        .collect();
+    let average_speed = if speeds.is_empty() {
+        None
+    } else {
+        Some(speeds.iter().sum::<usize>() as f64 / speeds.len() as f64)
+    };
+
+    DeviceReport {
+        mac,
+        hostname: sessions
+            .last()
+            .map(|s| s.client.hostname.clone())
+            .unwrap_or_default(),
+        connected_time,
+        sessions: sessions.len(),
+        longest_offline_gap,
+        typical_arrival,
+        typical_departure,
+        average_speed,
+    }
+}
+
+fn median_time_of_day(times: impl Iterator<Item = DateTime<Utc>>) -> Option<String> {
+    let seconds: Vec<u32> = times
+        .map(|time| time.with_timezone(&Local).num_seconds_from_midnight())
+        .collect();
+    let median = circular_median(&seconds)?;
+    Some(format!("{:02}:{:02}", median / 3600, median / 60 % 60))
+}
+
+// Median of times of the day, in seconds from midnight, on the 24-hour circle: the time that
+// minimizes the sum of the distances to the others, going either way around midnight.
+fn circular_median(seconds: &[u32]) -> Option<u32> {
+    const DAY: u32 = 24 * 3600;
+    let distance = |a: u32, b: u32| {
+        let d = a.abs_diff(b);
+        u64::from(d.min(DAY - d))
+    };
+    seconds.iter().copied().min_by_key(|&candidate| {
+        let total: u64 = seconds.iter().map(|&s| distance(candidate, s)).sum();
+        (total, candidate)
+    })
+}
+
+pub fn print_table(reports: &[DeviceReport]) {
+    println!(
+        "{:<17}  {:<20}  {:>12}  {:>8}  {:>12}  {:>7}  {:>9}  {:>9}",
+        "MAC", "Host", "Connected", "Sessions", "Longest gap", "Arrival", "Departure", "Speed"
+    );
+    for report in reports {
+        println!(
+            "{:<17}  {}  {:>12}  {:>8}  {:>12}  {:>7}  {:>9}  {:>9}",
+            report.mac.to_string(),
+            text::pad(&report.hostname, 20),
+            format_seconds(report.connected_time),
+            report.sessions,
+            report
+                .longest_offline_gap
+                .map_or_else(|| "-".to_owned(), format_seconds),
+            report.typical_arrival.as_deref().unwrap_or("-"),
+            report.typical_departure.as_deref().unwrap_or("-"),
+            report
+                .average_speed
+                .map_or_else(|| "-".to_owned(), |speed| format!("{:.1}", speed)),
+        );
+    }
+}
+
+pub fn print_json(reports: &[DeviceReport]) -> Result<(), Box<dyn std::error::Error>> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    serde_json::to_writer_pretty(&mut stdout, reports)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+pub fn print_csv(reports: &[DeviceReport]) -> Result<(), Box<dyn std::error::Error>> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    writeln!(
+        stdout,
+        "mac,hostname,connected_time,sessions,longest_offline_gap,typical_arrival,typical_departure,average_speed"
+    )?;
+    for report in reports {
+        writeln!(
+            stdout,
+            "{},{},{},{},{},{},{},{}",
+            report.mac,
+            csv_escape(&report.hostname),
+            report.connected_time,
+            report.sessions,
+            report
+                .longest_offline_gap
+                .map_or_else(String::new, |gap| gap.to_string()),
+            report.typical_arrival.as_deref().unwrap_or(""),
+            report.typical_departure.as_deref().unwrap_or(""),
+            report
+                .average_speed
+                .map_or_else(String::new, |speed| format!("{:.1}", speed)),
+        )?;
+    }
+    Ok(())
+}
+
+// Formats a duration, rounded to the minute if it spans more than an hour.
+fn format_seconds(seconds: i64) -> String {
+    let seconds = if seconds >= 3600 {
+        seconds / 60 * 60
+    } else {
+        seconds
+    };
+    history::format_duration(chrono::Duration::seconds(seconds))
+}
+
+/// Quotes a CSV field if needed, per RFC 4180.
+pub fn csv_escape(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time_of_day(hours: u32, minutes: u32) -> u32 {
+        hours * 3600 + minutes * 60
+    }
+
+    #[test]
+    fn median_within_a_day() {
+        let times = [time_of_day(8, 0), time_of_day(9, 0), time_of_day(8, 30)];
+        assert_eq!(circular_median(&times), Some(time_of_day(8, 30)));
+    }
+
+    #[test]
+    fn median_across_midnight() {
+        let times = [time_of_day(23, 30), time_of_day(0, 30), time_of_day(0, 10)];
+        assert_eq!(circular_median(&times), Some(time_of_day(0, 10)));
+    }
+
+    #[test]
+    fn median_of_nothing() {
+        assert_eq!(circular_median(&[]), None);
+    }
+
+    #[test]
+    fn calendar_periods() {
+        // A Wednesday afternoon.
+        let now = Local.with_ymd_and_hms(2021, 3, 17, 15, 20, 0).unwrap();
+        assert_eq!(
+            period_start(Period::Day, now),
+            Local.with_ymd_and_hms(2021, 3, 17, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            period_start(Period::Week, now),
+            Local.with_ymd_and_hms(2021, 3, 15, 0, 0, 0).unwrap()
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::types::{CmState, LanUserTable};
+use crate::types::{CmState, CmSystemInfo, DocsisChannels, GlobalSettings, LanUserTable, MacAddr};
 use async_trait::async_trait;
 
 #[async_trait(?Send)]
@@ -6,4 +6,11 @@ pub trait Router {
     async fn logout(&mut self) -> Result<(), Box<dyn std::error::Error>>;
     async fn devices(&mut self) -> Result<LanUserTable, Box<dyn std::error::Error>>;
     async fn temperature(&mut self) -> Result<CmState, Box<dyn std::error::Error>>;
+    async fn global_settings(&mut self) -> Result<GlobalSettings, Box<dyn std::error::Error>>;
+    async fn system_info(&mut self) -> Result<CmSystemInfo, Box<dyn std::error::Error>>;
+    async fn channels(&mut self) -> Result<DocsisChannels, Box<dyn std::error::Error>>;
+    /// Denies network access to a device.
+    async fn block_device(&mut self, mac: &MacAddr) -> Result<(), Box<dyn std::error::Error>>;
+    /// Restarts the router, which logs out the current session.
+    async fn reboot(&mut self) -> Result<(), Box<dyn std::error::Error>>;
 }
@@ -0,0 +1,396 @@
+//! Router session shared by concurrent tasks. The router invalidates its session token on each
+//! request, so requests can't overlap: they are queued to a single task that owns the router, and
+//! sent one at a time.
+
+use crate::router::Router;
+use crate::types::{CmState, CmSystemInfo, DocsisChannels, GlobalSettings, LanUserTable, MacAddr};
+use async_trait::async_trait;
+use futures::channel::{mpsc, oneshot};
+use futures::future::FutureExt;
+use futures::stream::StreamExt;
+use log::debug;
+use std::io;
+
+/// Errors of the shared router, which unlike those of `Router` can be sent across threads.
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+// Errors are sent back as their message, since those of the router aren't `Send`.
+type Reply<T> = oneshot::Sender<Result<T, String>>;
+
+enum Request {
+    Devices(Reply<LanUserTable>),
+    Temperature(Reply<CmState>),
+    GlobalSettings(Reply<GlobalSettings>),
+    SystemInfo(Reply<CmSystemInfo>),
+    Channels(Reply<DocsisChannels>),
+    BlockDevice(MacAddr, Reply<()>),
+    Reboot(Reply<()>),
+    Logout(Reply<()>),
+}
+
+/// Cloneable handle to the router session, which can be used from any thread.
+#[derive(Clone)]
+pub struct SharedRouter {
+    requests: mpsc::UnboundedSender<Request>,
+}
+
+// The handles are shared by the tasks of the web server.
+fn _assert_send_sync() {
+    fn _assert<T: Send + Sync>() {}
+    _assert::<SharedRouter>();
+}
+
+/// The end of the session that makes the requests on the router. Dropping it answers all the
+/// pending requests with an error.
+pub struct Session {
+    requests: mpsc::UnboundedReceiver<Request>,
+    // Request received while gathering the callers waiting for the devices, to be made next.
+    pending: Option<Request>,
+}
+
+/// Creates a handle to a router session, whose requests are made once the session is run.
+pub fn shared() -> (SharedRouter, Session) {
+    let (sender, receiver) = mpsc::unbounded();
+    (
+        SharedRouter { requests: sender },
+        Session {
+            requests: receiver,
+            pending: None,
+        },
+    )
+}
+
+impl Session {
+    /// Makes the queued requests on the router, until all the handles are dropped.
+    pub async fn run<R: Router>(mut self, router: &mut R) {
+        while let Some(request) = self.next().await {
+            match request {
+                Request::Devices(reply) => {
+                    // Callers asking for the devices while a request is queued or in progress
+                    // get its result, rather than making another request, unless they asked
+                    // after another request (e.g. a reboot) that must be made first.
+                    let mut waiters = vec![reply];
+                    self.take_devices_waiters(&mut waiters);
+                    let result = router.devices().await.map_err(|e| e.to_string());
+                    self.take_devices_waiters(&mut waiters);
+                    debug!("Sending the devices to {} callers", waiters.len());
+                    for waiter in waiters {
+                        let _ = waiter.send(result.clone());
+                    }
+                }
+                Request::Temperature(reply) => send(reply, router.temperature().await),
+                Request::GlobalSettings(reply) => send(reply, router.global_settings().await),
+                Request::SystemInfo(reply) => send(reply, router.system_info().await),
+                Request::Channels(reply) => send(reply, router.channels().await),
+                Request::BlockDevice(mac, reply) => send(reply, router.block_device(&mac).await),
+                Request::Reboot(reply) => send(reply, router.reboot().await),
+                Request::Logout(reply) => send(reply, router.logout().await),
+            }
+        }
+    }
+
+    async fn next(&mut self) -> Option<Request> {
+        match self.pending.take() {
+            Some(request) => Some(request),
+            None => self.requests.next().await,
+        }
+    }
+
+    // Takes the callers waiting for the devices at the head of the queue, up to another request,
+    // which is kept to be made next so that the requests are made in order.
+    fn take_devices_waiters(&mut self, waiters: &mut Vec<Reply<LanUserTable>>) {
+        if self.pending.is_some() {
+            return;
+        }
+        while let Some(Some(request)) = self.requests.next().now_or_never() {
+            match request {
+                Request::Devices(reply) => waiters.push(reply),
+                request => {
+                    self.pending = Some(request);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+// Sends a result back to the caller, who may have given up in the meantime.
+fn send<T>(reply: Reply<T>, result: Result<T, Box<dyn std::error::Error>>) {
+    let _ = reply.send(result.map_err(|e| e.to_string()));
+}
+
+impl SharedRouter {
+    /// Lists the devices. Callers asking for the devices while a request is already queued or in
+    /// progress get its result, rather than making another request, unless another kind of
+    /// request was queued in between.
+    pub async fn devices(&self) -> Result<LanUserTable, Error> {
+        self.request(Request::Devices).await
+    }
+
+    pub async fn temperature(&self) -> Result<CmState, Error> {
+        self.request(Request::Temperature).await
+    }
+
+    pub async fn global_settings(&self) -> Result<GlobalSettings, Error> {
+        self.request(Request::GlobalSettings).await
+    }
+
+    pub async fn system_info(&self) -> Result<CmSystemInfo, Error> {
+        self.request(Request::SystemInfo).await
+    }
+
+    pub async fn channels(&self) -> Result<DocsisChannels, Error> {
+        self.request(Request::Channels).await
+    }
+
+    pub async fn block_device(&self, mac: MacAddr) -> Result<(), Error> {
+        self.request(|reply| Request::BlockDevice(mac, reply)).await
+    }
+
+    pub async fn reboot(&self) -> Result<(), Error> {
+        self.request(Request::Reboot).await
+    }
+
+    pub async fn logout(&self) -> Result<(), Error> {
+        self.request(Request::Logout).await
+    }
+
+    async fn request<T>(&self, request: impl FnOnce(Reply<T>) -> Request) -> Result<T, Error> {
+        let (reply, result) = oneshot::channel();
+        self.requests
+            .unbounded_send(request(reply))
+            .map_err(|_| closed())?;
+        Self::result(result).await
+    }
+
+    async fn result<T>(result: oneshot::Receiver<Result<T, String>>) -> Result<T, Error> {
+        match result.await {
+            Ok(result) => result.map_err(|e| io::Error::new(io::ErrorKind::Other, e).into()),
+            Err(oneshot::Canceled) => Err(closed()),
+        }
+    }
+}
+
+fn closed() -> Error {
+    io::Error::new(io::ErrorKind::Other, "The router session is closed").into()
+}
+
+// Errors of the shared router, as those of `Router`.
+fn unshared<T>(result: Result<T, Error>) -> Result<T, Box<dyn std::error::Error>> {
+    result.map_err(|e| e as Box<dyn std::error::Error>)
+}
+
+// The shared router can be used in place of the router it wraps, e.g. by the monitoring loops.
+#[async_trait(?Send)]
+impl Router for SharedRouter {
+    async fn logout(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        unshared(SharedRouter::logout(self).await)
+    }
+
+    async fn devices(&mut self) -> Result<LanUserTable, Box<dyn std::error::Error>> {
+        unshared(SharedRouter::devices(self).await)
+    }
+
+    async fn temperature(&mut self) -> Result<CmState, Box<dyn std::error::Error>> {
+        unshared(SharedRouter::temperature(self).await)
+    }
+
+    async fn global_settings(&mut self) -> Result<GlobalSettings, Box<dyn std::error::Error>> {
+        unshared(SharedRouter::global_settings(self).await)
+    }
+
+    async fn system_info(&mut self) -> Result<CmSystemInfo, Box<dyn std::error::Error>> {
+        unshared(SharedRouter::system_info(self).await)
+    }
+
+    async fn channels(&mut self) -> Result<DocsisChannels, Box<dyn std::error::Error>> {
+        unshared(SharedRouter::channels(self).await)
+    }
+
+    async fn block_device(&mut self, mac: &MacAddr) -> Result<(), Box<dyn std::error::Error>> {
+        unshared(SharedRouter::block_device(self, *mac).await)
+    }
+
+    async fn reboot(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        unshared(SharedRouter::reboot(self).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Wifi;
+    use futures::future;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::time::Duration;
+    use tokio::time;
+
+    // Router whose device queries take a while, and are counted.
+    struct MockRouter {
+        devices_queries: Rc<Cell<usize>>,
+        // Requests made, in order.
+        log: Rc<RefCell<Vec<&'static str>>>,
+        delay: Duration,
+        fail: bool,
+    }
+
+    impl MockRouter {
+        fn new(delay: Duration) -> Self {
+            Self {
+                devices_queries: Rc::new(Cell::new(0)),
+                log: Rc::new(RefCell::new(Vec::new())),
+                delay,
+                fail: false,
+            }
+        }
+    }
+
+    fn unsupported<T>() -> Result<T, Box<dyn std::error::Error>> {
+        Err("Unsupported".into())
+    }
+
+    #[async_trait(?Send)]
+    impl Router for MockRouter {
+        async fn logout(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+
+        async fn devices(&mut self) -> Result<LanUserTable, Box<dyn std::error::Error>> {
+            self.devices_queries.set(self.devices_queries.get() + 1);
+            self.log.borrow_mut().push("devices");
+            time::delay_for(self.delay).await;
+            if self.fail {
+                return Err("Router error".into());
+            }
+            Ok(LanUserTable {
+                customer: String::new(),
+                total_client: self.devices_queries.get(),
+                wifi: Wifi {
+                    clientinfo: Vec::new(),
+                },
+            })
+        }
+
+        async fn temperature(&mut self) -> Result<CmState, Box<dyn std::error::Error>> {
+            unsupported()
+        }
+
+        async fn global_settings(&mut self) -> Result<GlobalSettings, Box<dyn std::error::Error>> {
+            unsupported()
+        }
+
+        async fn system_info(&mut self) -> Result<CmSystemInfo, Box<dyn std::error::Error>> {
+            unsupported()
+        }
+
+        async fn channels(&mut self) -> Result<DocsisChannels, Box<dyn std::error::Error>> {
+            unsupported()
+        }
+
+        async fn block_device(&mut self, _mac: &MacAddr) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+
+        async fn reboot(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.log.borrow_mut().push("reboot");
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn queued_devices_requests_are_coalesced() {
+        let (shared, session) = shared();
+        let mut router = MockRouter::new(Duration::from_millis(10));
+        let queries = router.devices_queries.clone();
+        let calls = async move {
+            future::join_all((0..5).map(|_| shared.devices()))
+                .await
+                .into_iter()
+                .map(|result| result.unwrap().total_client)
+                .collect::<Vec<_>>()
+        };
+        let ((), results) = future::join(session.run(&mut router), calls).await;
+        assert_eq!(results, [1; 5]);
+        assert_eq!(queries.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn devices_requests_in_progress_are_coalesced() {
+        let (shared, session) = shared();
+        let mut router = MockRouter::new(Duration::from_millis(100));
+        let queries = router.devices_queries.clone();
+        let calls = async move {
+            let first = shared.devices();
+            let later = async {
+                // While the first query is in progress.
+                time::delay_for(Duration::from_millis(20)).await;
+                future::join(shared.devices(), shared.devices()).await
+            };
+            let (first, (second, third)) = future::join(first, later).await;
+            // After the first query.
+            let fourth = shared.devices().await;
+            [first, second, third, fourth].map(|result| result.unwrap().total_client)
+        };
+        let ((), results) = future::join(session.run(&mut router), calls).await;
+        assert_eq!(results, [1, 1, 1, 2]);
+        assert_eq!(queries.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn requests_after_another_one_are_not_coalesced() {
+        let (shared, session) = shared();
+        let mut router = MockRouter::new(Duration::from_millis(10));
+        let log = router.log.clone();
+        let calls = async move {
+            let (before, reboot, after, again) = future::join4(
+                shared.devices(),
+                shared.reboot(),
+                shared.devices(),
+                shared.devices(),
+            )
+            .await;
+            reboot.unwrap();
+            [before, after, again].map(|result| result.unwrap().total_client)
+        };
+        let ((), results) = future::join(session.run(&mut router), calls).await;
+        assert_eq!(results, [1, 2, 2]);
+        assert_eq!(*log.borrow(), ["devices", "reboot", "devices"]);
+    }
+
+    #[tokio::test]
+    async fn errors_are_sent_to_all_callers() {
+        let (shared, session) = shared();
+        let mut router = MockRouter::new(Duration::from_millis(10));
+        router.fail = true;
+        let calls = async move { future::join(shared.devices(), shared.devices()).await };
+        let ((), (first, second)) = future::join(session.run(&mut router), calls).await;
+        assert_eq!(first.unwrap_err().to_string(), "Router error");
+        assert_eq!(second.unwrap_err().to_string(), "Router error");
+    }
+
+    #[tokio::test]
+    async fn requests_fail_once_the_session_is_dropped() {
+        let (shared, session) = shared();
+        drop(session);
+        assert!(shared.devices().await.is_err());
+        assert!(shared.reboot().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn requests_in_progress_fail_when_the_session_is_dropped() {
+        let (shared, session) = shared();
+        let mut router = MockRouter::new(Duration::from_secs(10));
+        let other = shared.clone();
+        let run = async {
+            // Interrupted during the first query.
+            let _ = time::timeout(Duration::from_millis(50), session.run(&mut router)).await;
+        };
+        let calls = future::join(shared.devices(), other.devices());
+        let ((), (first, second)) = future::join(run, calls).await;
+        assert!(first.is_err());
+        assert!(second.is_err());
+        // Later calls don't wait forever either.
+        assert!(shared.devices().await.is_err());
+    }
+}
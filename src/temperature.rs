@@ -0,0 +1,130 @@
+use crate::alert::Alert;
+use crate::types::CmState;
+use log::info;
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Temperature sensors of the router.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sensor {
+    Board,
+    Tuner,
+}
+
+impl Sensor {
+    fn read(self, state: &CmState) -> usize {
+        match self {
+            Sensor::Board => state.temperature,
+            Sensor::Tuner => state.tunner_temperature,
+        }
+    }
+}
+
+impl fmt::Display for Sensor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sensor::Board => f.write_str("temperature"),
+            Sensor::Tuner => f.write_str("tuner temperature"),
+        }
+    }
+}
+
+/// Current, minimal and maximal values of a sensor over the history window.
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    pub current: usize,
+    pub min: usize,
+    pub max: usize,
+}
+
+/// Rolling history of temperature readings.
+pub struct TemperatureHistory {
+    samples: VecDeque<(Instant, CmState)>,
+    window: Duration,
+}
+
+impl TemperatureHistory {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            window,
+        }
+    }
+
+    pub fn push(&mut self, state: CmState) {
+        let now = Instant::now();
+        while let Some((time, _)) = self.samples.front() {
+            if now.duration_since(*time) <= self.window {
+                break;
+            }
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now, state));
+    }
+
+    pub fn latest(&self) -> Option<&CmState> {
+        self.samples.back().map(|(_, state)| state)
+    }
+
+    pub fn stats(&self, sensor: Sensor) -> Option<Stats> {
+        let current = sensor.read(self.latest()?);
+        let values = self.samples.iter().map(|(_, state)| sensor.read(state));
+        Some(Stats {
+            current,
+            min: values.clone().min()?,
+            max: values.max()?,
+        })
+    }
+}
+
+/// Records temperature readings, and raises alerts when they cross the configured thresholds.
+pub struct TemperatureMonitor {
+    history: TemperatureHistory,
+    thresholds: Vec<(Sensor, usize)>,
+    exceeded: Vec<Sensor>,
+}
+
+impl TemperatureMonitor {
+    // Duration over which the history is kept.
+    const HISTORY_WINDOW: Duration = Duration::from_secs(24 * 3600);
+
+    pub fn new(max_temperature: Option<usize>, max_tuner_temperature: Option<usize>) -> Self {
+        let thresholds = max_temperature
+            .map(|t| (Sensor::Board, t))
+            .into_iter()
+            .chain(max_tuner_temperature.map(|t| (Sensor::Tuner, t)))
+            .collect();
+        Self {
+            history: TemperatureHistory::new(Self::HISTORY_WINDOW),
+            thresholds,
+            exceeded: Vec::new(),
+        }
+    }
+
+    pub fn history(&self) -> &TemperatureHistory {
+        &self.history
+    }
+
+    /// Records a new reading, and returns alerts for the sensors that crossed their threshold.
+    pub fn update(&mut self, state: CmState) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for &(sensor, threshold) in &self.thresholds {
+            let value = sensor.read(&state);
+            let was_exceeded = self.exceeded.contains(&sensor);
+            if value > threshold && !was_exceeded {
+                self.exceeded.push(sensor);
+                alerts.push(Alert::Temperature {
+                    sensor,
+                    value,
+                    threshold,
+                });
+            } else if value <= threshold && was_exceeded {
+                info!("The {} is back to {}°C", sensor, value);
+                self.exceeded.retain(|&s| s != sensor);
+            }
+        }
+        self.history.push(state);
+        alerts
+    }
+}
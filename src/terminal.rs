@@ -0,0 +1,148 @@
+//! Terminal on which the TUI is drawn, with a backend chosen at build time: the ncurses system
+//! library by default, or crossterm, which is pure Rust.
+
+#[cfg(feature = "crossterm-backend")]
+mod crossterm_backend;
+#[cfg(feature = "ncurses-backend")]
+mod ncurses_backend;
+
+#[cfg(not(any(feature = "ncurses-backend", feature = "crossterm-backend")))]
+compile_error!("Either the ncurses-backend or the crossterm-backend feature must be enabled");
+// Otherwise libncurses would still be linked, e.g. in a static build with crossterm.
+#[cfg(all(feature = "ncurses-backend", feature = "crossterm-backend"))]
+compile_error!(
+    "The ncurses-backend and crossterm-backend features are exclusive, build with \
+     --no-default-features --features crossterm-backend to use crossterm"
+);
+
+use crate::theme::Style;
+use std::ops::BitOr;
+
+/// Drawing and input primitives of the TUI. Drawing goes to an off-screen buffer, which is only
+/// sent to the terminal on refresh.
+pub trait Terminal {
+    /// Number of colors supported by the terminal, or 0 if it doesn't support colors.
+    fn colors(&self) -> i32;
+    /// Sets the foreground and background colors of a style, given as indices in the 8 or 256
+    /// color palettes.
+    fn init_style(&self, style: Style, foreground: i16, background: i16);
+
+    /// Number of rows and columns of the terminal.
+    fn size(&self) -> (i32, i32);
+    /// Clears the buffer, and takes the new size of the terminal into account.
+    fn erase(&self);
+    /// Sends what changed in the buffer since the last refresh to the terminal.
+    fn refresh(&self);
+
+    /// Enables attributes for the following text. A style replaces the current one.
+    fn attr_on(&self, attributes: Attributes);
+    /// Disables attributes for the following text.
+    fn attr_off(&self, attributes: Attributes);
+    /// Prints text at a position, clipped to the edge of the terminal.
+    fn print(&self, y: i32, x: i32, s: &str);
+    /// Draws the border of a box.
+    fn draw_box(&self, top: i32, left: i32, height: i32, width: i32);
+
+    /// Returns the next pending key press or mouse event, without blocking.
+    fn poll_key(&self) -> Option<Key>;
+}
+
+/// Opens the terminal of the backend enabled at build time.
+pub fn open() -> Box<dyn Terminal> {
+    #[cfg(feature = "crossterm-backend")]
+    return Box::new(crossterm_backend::CrosstermTerminal::new());
+    #[cfg(feature = "ncurses-backend")]
+    return Box::new(ncurses_backend::NcursesTerminal::new());
+}
+
+/// Text attributes, with an optional color style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Attributes {
+    flags: u8,
+    style: Option<Style>,
+}
+
+impl Attributes {
+    pub const NORMAL: Attributes = Attributes::flags(0);
+    pub const BOLD: Attributes = Attributes::flags(1);
+    pub const DIM: Attributes = Attributes::flags(2);
+    pub const UNDERLINE: Attributes = Attributes::flags(4);
+    pub const REVERSE: Attributes = Attributes::flags(8);
+
+    const fn flags(flags: u8) -> Self {
+        Attributes { flags, style: None }
+    }
+
+    /// Colors of a style, as set with `Terminal::init_style`.
+    pub fn style(style: Style) -> Self {
+        Attributes {
+            flags: 0,
+            style: Some(style),
+        }
+    }
+
+    pub fn contains(self, other: Attributes) -> bool {
+        self.flags & other.flags == other.flags
+    }
+
+    pub fn color_style(self) -> Option<Style> {
+        self.style
+    }
+
+    // Attributes after enabling others.
+    fn with(self, other: Attributes) -> Self {
+        Attributes {
+            flags: self.flags | other.flags,
+            style: other.style.or(self.style),
+        }
+    }
+
+    // Attributes after disabling others.
+    #[cfg_attr(not(feature = "crossterm-backend"), allow(dead_code))]
+    fn without(self, other: Attributes) -> Self {
+        Attributes {
+            flags: self.flags & !other.flags,
+            style: if other.style.is_some() {
+                None
+            } else {
+                self.style
+            },
+        }
+    }
+}
+
+impl BitOr for Attributes {
+    type Output = Attributes;
+
+    fn bitor(self, other: Attributes) -> Attributes {
+        self.with(other)
+    }
+}
+
+/// A key press, or a mouse event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    /// Ctrl-C, in backends where it doesn't raise SIGINT.
+    #[cfg_attr(not(feature = "crossterm-backend"), allow(dead_code))]
+    Interrupt,
+    /// The terminal was resized.
+    Resize,
+    /// The left mouse button was pressed at a column and row.
+    Click {
+        x: i32,
+        y: i32,
+    },
+    WheelUp,
+    WheelDown,
+}
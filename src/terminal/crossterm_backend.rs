@@ -0,0 +1,373 @@
+use super::{Attributes, Key, Terminal};
+use crate::theme::Style;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers, MouseButton, MouseEventKind};
+use crossterm::style::{
+    Attribute, Color, Print, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::{cursor, terminal, QueueableCommand};
+use std::cell::{Cell, RefCell};
+use std::io::{self, Write};
+use std::time::Duration;
+use unicode_width::UnicodeWidthChar;
+
+// A character on the screen, with its attributes. Like with ncurses, a wide character takes two
+// cells, the second of which is empty, and combining characters are kept with the character they
+// apply to.
+#[derive(Clone, PartialEq, Eq)]
+struct ScreenCell {
+    text: String,
+    attributes: Attributes,
+}
+
+impl ScreenCell {
+    fn blank() -> Self {
+        Self {
+            text: " ".to_owned(),
+            attributes: Attributes::NORMAL,
+        }
+    }
+
+    fn is_blank(&self) -> bool {
+        self.text == " " && self.attributes == Attributes::NORMAL
+    }
+
+    // Whether the cell is the second half of a wide character.
+    fn is_wide_continuation(&self) -> bool {
+        self.text.is_empty()
+    }
+}
+
+// Blanks the wide characters of a row that are only partly covered by the cells from `start` to
+// `end` (excluded), which are about to be overwritten.
+fn split_wide_characters(row: &mut [ScreenCell], start: usize, end: usize) {
+    if start > 0 && row[start].is_wide_continuation() {
+        row[start - 1] = ScreenCell::blank();
+    }
+    if end < row.len() && row[end].is_wide_continuation() {
+        row[end] = ScreenCell::blank();
+    }
+}
+
+// Prints text on a row of the screen from a column, clipped to the end of the row.
+fn print_row(row: &mut [ScreenCell], mut x: usize, s: &str, attributes: Attributes) {
+    // Cell of the last printed character, to which combining characters are added.
+    let mut last: Option<usize> = None;
+    for c in s.chars() {
+        match c.width() {
+            Some(0) => {
+                if let Some(last) = last {
+                    row[last].text.push(c);
+                }
+            }
+            Some(c_width) if x + c_width <= row.len() => {
+                split_wide_characters(row, x, x + c_width);
+                row[x] = ScreenCell {
+                    text: c.to_string(),
+                    attributes,
+                };
+                for cell in &mut row[x + 1..x + c_width] {
+                    *cell = ScreenCell {
+                        text: String::new(),
+                        attributes,
+                    };
+                }
+                last = Some(x);
+                x += c_width;
+            }
+            // Control characters aren't printed.
+            None => (),
+            // Clipped at the edge of the terminal.
+            Some(_) => break,
+        }
+    }
+}
+
+// Like ncurses, the screen is drawn to a buffer, and only the cells that differ from what the
+// terminal shows are sent on refresh.
+pub struct CrosstermTerminal {
+    size: Cell<(u16, u16)>,
+    buffer: RefCell<Vec<ScreenCell>>,
+    // What the terminal shows, or None if it must be fully repainted.
+    displayed: RefCell<Option<Vec<ScreenCell>>>,
+    attributes: Cell<Attributes>,
+    colors: i32,
+    styles: RefCell<[Option<(i16, i16)>; Style::ALL.len()]>,
+}
+
+impl CrosstermTerminal {
+    pub fn new() -> Self {
+        let mut stdout = io::stdout();
+        // Errors are ignored, like ncurses does when the terminal lacks a capability.
+        let _ = terminal::enable_raw_mode();
+        let _ = stdout
+            .queue(terminal::EnterAlternateScreen)
+            .and_then(|stdout| stdout.queue(event::EnableMouseCapture))
+            .and_then(|stdout| stdout.queue(cursor::Hide))
+            .map(|_| ());
+        let _ = stdout.flush();
+
+        let size = terminal::size().unwrap_or((80, 24));
+        Self {
+            size: Cell::new(size),
+            buffer: RefCell::new(vec![ScreenCell::blank(); size.0 as usize * size.1 as usize]),
+            displayed: RefCell::new(None),
+            attributes: Cell::new(Attributes::NORMAL),
+            colors: Self::detect_colors(),
+            styles: RefCell::new([None; Style::ALL.len()]),
+        }
+    }
+
+    // Number of colors of the terminal, guessed from the environment since crossterm doesn't read
+    // the terminfo database.
+    fn detect_colors() -> i32 {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        if term.is_empty() || term == "dumb" {
+            0
+        } else if term.contains("256color") || colorterm == "truecolor" || colorterm == "24bit" {
+            256
+        } else {
+            8
+        }
+    }
+
+    // Sends a cell to the terminal, after setting its attributes if they differ from those of the
+    // previous cell.
+    fn queue_cell(
+        &self,
+        stdout: &mut impl Write,
+        cell: &ScreenCell,
+        current: &mut Option<Attributes>,
+    ) -> crossterm::Result<()> {
+        if *current != Some(cell.attributes) {
+            stdout.queue(SetAttribute(Attribute::Reset))?;
+            for &(flag, attribute) in [
+                (Attributes::BOLD, Attribute::Bold),
+                (Attributes::DIM, Attribute::Dim),
+                (Attributes::UNDERLINE, Attribute::Underlined),
+                (Attributes::REVERSE, Attribute::Reverse),
+            ]
+            .iter()
+            {
+                if cell.attributes.contains(flag) {
+                    stdout.queue(SetAttribute(attribute))?;
+                }
+            }
+            let colors = cell
+                .attributes
+                .color_style()
+                .and_then(|style| self.styles.borrow()[style.pair() as usize - 1]);
+            if let Some((foreground, background)) = colors {
+                stdout.queue(SetForegroundColor(Color::AnsiValue(foreground as u8)))?;
+                stdout.queue(SetBackgroundColor(Color::AnsiValue(background as u8)))?;
+            }
+            *current = Some(cell.attributes);
+        }
+        stdout.queue(Print(&cell.text))?;
+        Ok(())
+    }
+
+    fn draw(&self) -> crossterm::Result<()> {
+        let (width, _) = self.size.get();
+        let buffer = self.buffer.borrow();
+        let mut displayed = self.displayed.borrow_mut();
+        let mut stdout = io::stdout();
+
+        if displayed.is_none() {
+            stdout.queue(terminal::Clear(terminal::ClearType::All))?;
+        }
+        let mut current = None;
+        // Position right after the last sent cell, where the next one can be printed without
+        // moving the cursor.
+        let mut cursor = None;
+        for (i, cell) in buffer.iter().enumerate() {
+            // The second half of a wide character is drawn along with the first one, which
+            // changes whenever the second half does.
+            if cell.is_wide_continuation() {
+                continue;
+            }
+            if let Some(old) = displayed.as_ref() {
+                if old.get(i) == Some(cell) {
+                    continue;
+                }
+            } else if cell.is_blank() {
+                continue;
+            }
+            let position = ((i % width as usize) as u16, (i / width as usize) as u16);
+            if cursor != Some(position) {
+                stdout.queue(cursor::MoveTo(position.0, position.1))?;
+            }
+            self.queue_cell(&mut stdout, cell, &mut current)?;
+            let wide = buffer
+                .get(i + 1)
+                .map_or(false, ScreenCell::is_wide_continuation);
+            cursor = Some((position.0 + if wide { 2 } else { 1 }, position.1));
+        }
+        stdout.queue(SetAttribute(Attribute::Reset))?;
+        stdout.flush()?;
+        *displayed = Some(buffer.clone());
+        Ok(())
+    }
+}
+
+impl Terminal for CrosstermTerminal {
+    fn colors(&self) -> i32 {
+        self.colors
+    }
+
+    fn init_style(&self, style: Style, foreground: i16, background: i16) {
+        self.styles.borrow_mut()[style.pair() as usize - 1] = Some((foreground, background));
+    }
+
+    fn size(&self) -> (i32, i32) {
+        let (width, height) = self.size.get();
+        (height as i32, width as i32)
+    }
+
+    fn erase(&self) {
+        let size = terminal::size().unwrap_or_else(|_| self.size.get());
+        if size != self.size.get() {
+            self.size.set(size);
+            *self.displayed.borrow_mut() = None;
+        }
+        let mut buffer = self.buffer.borrow_mut();
+        buffer.clear();
+        buffer.resize(size.0 as usize * size.1 as usize, ScreenCell::blank());
+    }
+
+    fn refresh(&self) {
+        // Like with ncurses, a failure to draw shows up on the screen rather than as an error.
+        let _ = self.draw();
+    }
+
+    fn attr_on(&self, attributes: Attributes) {
+        self.attributes.set(self.attributes.get().with(attributes));
+    }
+
+    fn attr_off(&self, attributes: Attributes) {
+        self.attributes
+            .set(self.attributes.get().without(attributes));
+    }
+
+    fn print(&self, y: i32, x: i32, s: &str) {
+        let (width, height) = self.size.get();
+        let (width, height) = (width as i32, height as i32);
+        if y < 0 || y >= height || x < 0 {
+            return;
+        }
+        let attributes = self.attributes.get();
+        let mut buffer = self.buffer.borrow_mut();
+        let start = (y * width) as usize;
+        print_row(
+            &mut buffer[start..start + width as usize],
+            x as usize,
+            s,
+            attributes,
+        );
+    }
+
+    fn draw_box(&self, top: i32, left: i32, height: i32, width: i32) {
+        let (bottom, right) = (top + height - 1, left + width - 1);
+        let line = "─".repeat((width - 2).max(0) as usize);
+        self.print(top, left, &format!("┌{}┐", line));
+        self.print(bottom, left, &format!("└{}┘", line));
+        for y in top + 1..bottom {
+            self.print(y, left, "│");
+            self.print(y, right, "│");
+        }
+    }
+
+    fn poll_key(&self) -> Option<Key> {
+        while let Ok(true) = event::poll(Duration::from_secs(0)) {
+            let key = match event::read() {
+                Ok(Event::Key(key)) => match key.code {
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        Some(Key::Interrupt)
+                    }
+                    KeyCode::Char(c) => Some(Key::Char(c)),
+                    KeyCode::Enter => Some(Key::Enter),
+                    KeyCode::Esc => Some(Key::Escape),
+                    KeyCode::Tab => Some(Key::Tab),
+                    KeyCode::Backspace => Some(Key::Backspace),
+                    KeyCode::Up => Some(Key::Up),
+                    KeyCode::Down => Some(Key::Down),
+                    KeyCode::PageUp => Some(Key::PageUp),
+                    KeyCode::PageDown => Some(Key::PageDown),
+                    KeyCode::Home => Some(Key::Home),
+                    KeyCode::End => Some(Key::End),
+                    _ => None,
+                },
+                Ok(Event::Mouse(mouse)) => match mouse.kind {
+                    MouseEventKind::Down(MouseButton::Left) => Some(Key::Click {
+                        x: mouse.column as i32,
+                        y: mouse.row as i32,
+                    }),
+                    MouseEventKind::ScrollUp => Some(Key::WheelUp),
+                    MouseEventKind::ScrollDown => Some(Key::WheelDown),
+                    _ => None,
+                },
+                Ok(Event::Resize(_, _)) => Some(Key::Resize),
+                Err(_) => return None,
+            };
+            if key.is_some() {
+                return key;
+            }
+        }
+        None
+    }
+}
+
+impl Drop for CrosstermTerminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = stdout
+            .queue(cursor::Show)
+            .and_then(|stdout| stdout.queue(event::DisableMouseCapture))
+            .and_then(|stdout| stdout.queue(terminal::LeaveAlternateScreen))
+            .map(|_| ());
+        let _ = stdout.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(width: usize) -> Vec<ScreenCell> {
+        vec![ScreenCell::blank(); width]
+    }
+
+    fn texts(row: &[ScreenCell]) -> Vec<&str> {
+        row.iter().map(|cell| cell.text.as_str()).collect()
+    }
+
+    #[test]
+    fn wide_characters_take_two_cells() {
+        let mut row = row(6);
+        print_row(&mut row, 0, "电脑ab", Attributes::NORMAL);
+        assert_eq!(texts(&row), ["电", "", "脑", "", "a", "b"]);
+    }
+
+    #[test]
+    fn combining_characters_stay_with_their_character() {
+        let mut row = row(4);
+        print_row(&mut row, 0, "e\u{301}te", Attributes::NORMAL);
+        assert_eq!(texts(&row), ["e\u{301}", "t", "e", " "]);
+    }
+
+    #[test]
+    fn clipped_at_the_end_of_the_row() {
+        let mut row = row(3);
+        print_row(&mut row, 1, "a电", Attributes::NORMAL);
+        assert_eq!(texts(&row), [" ", "a", " "]);
+    }
+
+    #[test]
+    fn overwritten_halves_of_wide_characters_are_blanked() {
+        let mut row = row(6);
+        print_row(&mut row, 0, "电脑", Attributes::NORMAL);
+        print_row(&mut row, 1, "ab", Attributes::NORMAL);
+        assert_eq!(texts(&row), [" ", "a", "b", " ", " ", " "]);
+    }
+}
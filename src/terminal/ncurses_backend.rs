@@ -0,0 +1,193 @@
+use super::{Attributes, Key, Terminal};
+use crate::theme::Style;
+use std::cell::RefCell;
+
+pub struct NcursesTerminal {
+    window: *mut i8,
+    // Bytes of an incomplete UTF-8 character being typed.
+    pending_input: RefCell<Vec<u8>>,
+}
+
+impl NcursesTerminal {
+    pub fn new() -> Self {
+        ncurses::setlocale(ncurses::LcCategory::all, "");
+        let window = ncurses::initscr();
+        ncurses::cbreak();
+        ncurses::noecho();
+        ncurses::intrflush(window, false);
+        // Input is polled from the refresh loop, so reading keys must not block.
+        ncurses::keypad(window, true);
+        ncurses::nodelay(window, true);
+        // Don't wait a full second to tell the escape key apart from escape sequences.
+        ncurses::set_escdelay(25);
+        ncurses::curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_INVISIBLE);
+        // Report button presses right away, rather than waiting to detect clicks.
+        ncurses::mousemask(
+            (ncurses::BUTTON1_PRESSED | ncurses::BUTTON4_PRESSED | ncurses::BUTTON5_PRESSED)
+                as ncurses::mmask_t,
+            None,
+        );
+        ncurses::mouseinterval(0);
+        if ncurses::has_colors() {
+            ncurses::start_color();
+        }
+
+        Self {
+            window,
+            pending_input: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn mouse_event(&self) -> Option<Key> {
+        let mut event = ncurses::MEVENT {
+            id: 0,
+            x: 0,
+            y: 0,
+            z: 0,
+            bstate: 0,
+        };
+        if ncurses::getmouse(&mut event) != ncurses::OK {
+            return None;
+        }
+        let pressed = |button: i32| event.bstate & button as ncurses::mmask_t != 0;
+        if pressed(ncurses::BUTTON4_PRESSED) {
+            Some(Key::WheelUp)
+        } else if pressed(ncurses::BUTTON5_PRESSED) {
+            Some(Key::WheelDown)
+        } else if pressed(ncurses::BUTTON1_PRESSED) {
+            Some(Key::Click {
+                x: event.x,
+                y: event.y,
+            })
+        } else {
+            None
+        }
+    }
+
+    // Characters are read byte by byte, so multi-byte characters are buffered until they are
+    // complete.
+    fn utf8_byte(&self, byte: u8) -> Option<Key> {
+        let mut pending = self.pending_input.borrow_mut();
+        pending.push(byte);
+        match std::str::from_utf8(&pending) {
+            Ok(s) => {
+                let c = s.chars().next();
+                pending.clear();
+                c.map(Key::Char)
+            }
+            Err(e) => {
+                if e.error_len().is_some() {
+                    pending.clear();
+                }
+                None
+            }
+        }
+    }
+
+    fn attr_t(attributes: Attributes) -> ncurses::attr_t {
+        let mut attr = ncurses::A_NORMAL();
+        for &(flag, a) in [
+            (Attributes::BOLD, ncurses::A_BOLD()),
+            (Attributes::DIM, ncurses::A_DIM()),
+            (Attributes::UNDERLINE, ncurses::A_UNDERLINE()),
+            (Attributes::REVERSE, ncurses::A_REVERSE()),
+        ]
+        .iter()
+        {
+            if attributes.contains(flag) {
+                attr |= a;
+            }
+        }
+        if let Some(style) = attributes.color_style() {
+            attr |= ncurses::COLOR_PAIR(style.pair());
+        }
+        attr
+    }
+}
+
+impl Terminal for NcursesTerminal {
+    fn colors(&self) -> i32 {
+        if ncurses::has_colors() {
+            ncurses::COLORS()
+        } else {
+            0
+        }
+    }
+
+    fn init_style(&self, style: Style, foreground: i16, background: i16) {
+        ncurses::init_pair(style.pair(), foreground, background);
+    }
+
+    fn size(&self) -> (i32, i32) {
+        let (mut y, mut x) = (0, 0);
+        ncurses::getmaxyx(self.window, &mut y, &mut x);
+        (y, x)
+    }
+
+    fn erase(&self) {
+        // Unlike clear(), erase() doesn't force a full repaint, so that refresh() only sends the
+        // characters that changed since the last draw.
+        ncurses::erase();
+    }
+
+    fn refresh(&self) {
+        ncurses::refresh();
+    }
+
+    fn attr_on(&self, attributes: Attributes) {
+        ncurses::attron(Self::attr_t(attributes));
+    }
+
+    fn attr_off(&self, attributes: Attributes) {
+        ncurses::attroff(Self::attr_t(attributes));
+    }
+
+    fn print(&self, y: i32, x: i32, s: &str) {
+        ncurses::wmove(self.window, y, x);
+        ncurses::addstr(s);
+    }
+
+    fn draw_box(&self, top: i32, left: i32, height: i32, width: i32) {
+        let (bottom, right) = (top + height - 1, left + width - 1);
+        ncurses::mvhline(top, left + 1, ncurses::ACS_HLINE(), width - 2);
+        ncurses::mvhline(bottom, left + 1, ncurses::ACS_HLINE(), width - 2);
+        ncurses::mvvline(top + 1, left, ncurses::ACS_VLINE(), height - 2);
+        ncurses::mvvline(top + 1, right, ncurses::ACS_VLINE(), height - 2);
+        ncurses::mvaddch(top, left, ncurses::ACS_ULCORNER());
+        ncurses::mvaddch(top, right, ncurses::ACS_URCORNER());
+        ncurses::mvaddch(bottom, left, ncurses::ACS_LLCORNER());
+        ncurses::mvaddch(bottom, right, ncurses::ACS_LRCORNER());
+    }
+
+    fn poll_key(&self) -> Option<Key> {
+        loop {
+            let key = match ncurses::getch() {
+                ncurses::ERR => return None,
+                // ncurses handles SIGWINCH by resizing its windows and queuing this key.
+                ncurses::KEY_RESIZE => Some(Key::Resize),
+                ncurses::KEY_MOUSE => self.mouse_event(),
+                ncurses::KEY_UP => Some(Key::Up),
+                ncurses::KEY_DOWN => Some(Key::Down),
+                ncurses::KEY_PPAGE => Some(Key::PageUp),
+                ncurses::KEY_NPAGE => Some(Key::PageDown),
+                ncurses::KEY_HOME => Some(Key::Home),
+                ncurses::KEY_END => Some(Key::End),
+                ncurses::KEY_ENTER | 0x0A | 0x0D => Some(Key::Enter),
+                ncurses::KEY_BACKSPACE | 0x08 | 0x7F => Some(Key::Backspace),
+                0x09 => Some(Key::Tab),
+                0x1B => Some(Key::Escape),
+                key @ 0x20..=0xFF => self.utf8_byte(key as u8),
+                _ => None,
+            };
+            if key.is_some() {
+                return key;
+            }
+        }
+    }
+}
+
+impl Drop for NcursesTerminal {
+    fn drop(&mut self) {
+        ncurses::endwin();
+    }
+}
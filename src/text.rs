@@ -0,0 +1,53 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Number of terminal columns taken by a string: wide characters (e.g. CJK) take two columns, and
+/// combining characters none.
+pub fn width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// Truncates a string to at most `width` terminal columns, without splitting a character from the
+/// combining characters that follow it.
+pub fn truncate(s: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (i, c) in s.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            return &s[..i];
+        }
+    }
+    s
+}
+
+/// Truncates a string to `width` terminal columns, and pads it with spaces to fill them.
+pub fn pad(s: &str, width: usize) -> String {
+    let s = truncate(s, width);
+    format!("{}{}", s, " ".repeat(width - self::width(s)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii() {
+        assert_eq!(width("laptop"), 6);
+        assert_eq!(truncate("laptop", 3), "lap");
+        assert_eq!(truncate("laptop", 10), "laptop");
+        assert_eq!(pad("tv", 4), "tv  ");
+    }
+
+    #[test]
+    fn wide_characters() {
+        assert_eq!(width("电脑"), 4);
+        assert_eq!(truncate("电脑", 3), "电");
+        assert_eq!(pad("电脑", 3), "电 ");
+    }
+
+    #[test]
+    fn combining_characters() {
+        let name = "Rene\u{301}e";
+        assert_eq!(width(name), 5);
+        assert_eq!(truncate(name, 4), "Rene\u{301}");
+    }
+}
@@ -0,0 +1,126 @@
+use crate::terminal::Attributes;
+use std::str::FromStr;
+
+// Colors of the 8-color palette.
+const COLOR_BLACK: i16 = 0;
+const COLOR_RED: i16 = 1;
+const COLOR_GREEN: i16 = 2;
+const COLOR_YELLOW: i16 = 3;
+const COLOR_BLUE: i16 = 4;
+const COLOR_MAGENTA: i16 = 5;
+const COLOR_CYAN: i16 = 6;
+const COLOR_WHITE: i16 = 7;
+
+/// Color scheme of the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Default,
+    /// Blue and orange instead of green and red, which are hard to tell apart with the most
+    /// common forms of color blindness.
+    Colorblind,
+    /// No colors, differences are shown with markers and text attributes.
+    Mono,
+}
+
+/// What a piece of text highlights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// A device that joined, or a value that appeared or increased.
+    Added,
+    /// A value that disappeared, decreased or is about to expire.
+    Removed,
+    /// A value that changed.
+    Changed,
+    /// Signal quality of a DOCSIS channel.
+    Good,
+    Marginal,
+    Bad,
+}
+
+impl Style {
+    pub const ALL: [Style; 6] = [
+        Style::Added,
+        Style::Removed,
+        Style::Changed,
+        Style::Good,
+        Style::Marginal,
+        Style::Bad,
+    ];
+
+    /// Index of the color pair of this style.
+    pub fn pair(self) -> i16 {
+        Style::ALL.iter().position(|&style| style == self).unwrap() as i16 + 1
+    }
+
+    /// Text attributes standing in for colors on monochrome terminals.
+    pub fn mono_attributes(self) -> Attributes {
+        match self {
+            Style::Added | Style::Changed | Style::Marginal => Attributes::BOLD,
+            Style::Removed => Attributes::UNDERLINE,
+            Style::Good => Attributes::NORMAL,
+            Style::Bad => Attributes::BOLD | Attributes::UNDERLINE,
+        }
+    }
+}
+
+impl Theme {
+    pub const NAMES: [&'static str; 3] = ["default", "colorblind", "mono"];
+
+    /// Foreground and background colors of a style, given the number of colors supported by the
+    /// terminal. Returns None for the monochrome theme.
+    pub fn colors(self, style: Style, colors: i32) -> Option<(i16, i16)> {
+        // Colors of the 256-color palette.
+        const DARK_GREEN: i16 = 22;
+        const DARK_RED: i16 = 88;
+        const GREY: i16 = 250;
+        const BRIGHT_WHITE: i16 = 231;
+        const GREEN: i16 = 41;
+        const AMBER: i16 = 214;
+        const VERMILION: i16 = 166;
+        const BLUE: i16 = 25;
+        const SKY_BLUE: i16 = 74;
+        const ORANGE: i16 = 208;
+        const YELLOW: i16 = 227;
+
+        let rich = colors >= 256;
+        let colors = match (self, style) {
+            (Theme::Mono, _) => return None,
+            (Theme::Default, Style::Added) if rich => (BRIGHT_WHITE, DARK_GREEN),
+            (Theme::Default, Style::Added) => (COLOR_BLACK, COLOR_GREEN),
+            (Theme::Default, Style::Removed) if rich => (BRIGHT_WHITE, DARK_RED),
+            (Theme::Default, Style::Removed) => (COLOR_WHITE, COLOR_RED),
+            (Theme::Default, Style::Good) if rich => (GREEN, COLOR_BLACK),
+            (Theme::Default, Style::Good) => (COLOR_GREEN, COLOR_BLACK),
+            (Theme::Default, Style::Marginal) if rich => (AMBER, COLOR_BLACK),
+            (Theme::Default, Style::Marginal) => (COLOR_YELLOW, COLOR_BLACK),
+            (Theme::Default, Style::Bad) if rich => (VERMILION, COLOR_BLACK),
+            (Theme::Default, Style::Bad) => (COLOR_RED, COLOR_BLACK),
+            (Theme::Colorblind, Style::Added) if rich => (BRIGHT_WHITE, BLUE),
+            (Theme::Colorblind, Style::Added) => (COLOR_WHITE, COLOR_BLUE),
+            (Theme::Colorblind, Style::Removed) if rich => (COLOR_BLACK, ORANGE),
+            (Theme::Colorblind, Style::Removed) => (COLOR_BLACK, COLOR_YELLOW),
+            (Theme::Colorblind, Style::Good) if rich => (SKY_BLUE, COLOR_BLACK),
+            (Theme::Colorblind, Style::Good) => (COLOR_CYAN, COLOR_BLACK),
+            (Theme::Colorblind, Style::Marginal) if rich => (YELLOW, COLOR_BLACK),
+            (Theme::Colorblind, Style::Marginal) => (COLOR_YELLOW, COLOR_BLACK),
+            (Theme::Colorblind, Style::Bad) if rich => (ORANGE, COLOR_BLACK),
+            (Theme::Colorblind, Style::Bad) => (COLOR_MAGENTA, COLOR_BLACK),
+            (_, Style::Changed) if rich => (COLOR_BLACK, GREY),
+            (_, Style::Changed) => (COLOR_BLACK, COLOR_WHITE),
+        };
+        Some(colors)
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Theme::Default),
+            "colorblind" => Ok(Theme::Colorblind),
+            "mono" => Ok(Theme::Mono),
+            _ => Err(format!("Unknown theme {:?}", s)),
+        }
+    }
+}
@@ -1,11 +1,63 @@
-use crate::types::{ClientInfo, LanUserTable};
+use crate::alias::Aliases;
+use crate::columns::Column;
+use crate::commands::Command;
+use crate::docsis::{self, ChannelHistory, Quality};
+use crate::history;
+use crate::temperature::{Sensor, TemperatureHistory};
+use crate::terminal::{self, Attributes, Key, Terminal};
+use crate::text::{self, truncate};
+use crate::theme::{Style, Theme};
+use crate::types::{
+    format_lease_time, ClientInfo, CmState, CmSystemInfo, DocsisChannels, DownstreamChannel,
+    GlobalSettings, InterfaceKind, LanUserTable, MacAddr, UpstreamChannel,
+};
+use crate::vendor;
+use chrono::{DateTime, Local};
 use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+use std::time::Duration;
 
 pub struct Tui {
-    window: *mut i8,
+    terminal: Box<dyn Terminal>,
     size: Size,
-    clients: Vec<ClientInfo>,
-    has_colors: bool,
+    // Configured columns of the device table, and their current layout.
+    columns: Vec<Column>,
+    layout: Vec<ColumnLayout>,
+    show_ipv6: bool,
+    rows: Vec<Row>,
+    // Indices of the displayed rows, after filtering and sorting.
+    view: Vec<usize>,
+    // Index of the selected row, and of the first displayed row, within the view.
+    selected: usize,
+    scroll: usize,
+    // Column by which the device table is sorted.
+    sort: Column,
+    descending: bool,
+    filter: String,
+    mode: Mode,
+    aliases: Aliases,
+    seen: HashMap<MacAddr, Seen>,
+    // How long departed devices stay in the table, and how long new devices are highlighted.
+    linger: chrono::Duration,
+    joined_highlight: chrono::Duration,
+    screen: Screen,
+    // Formatted statistics of the board and tuner temperatures.
+    temperature: (String, String),
+    cm_state: Option<CmState>,
+    modem: Option<ModemStatus>,
+    channel_history: ChannelHistory,
+    // Index of the first displayed line of the channel tables.
+    channel_scroll: usize,
+    // Feedback about the last action, shown in the status bar until the next key press.
+    message: Option<String>,
+    last_refresh: Option<DateTime<Local>>,
+    // Devices of the last displayed refresh, saved by snapshots.
+    table: Option<LanUserTable>,
+    // Data received while the display is frozen.
+    paused: Option<Pending>,
+    // Monochrome if the terminal doesn't support colors.
+    theme: Theme,
 }
 
 struct Size {
@@ -13,221 +65,1559 @@ struct Size {
     y: i32,
 }
 
+// A displayed column of the device table, with its position and width.
+struct ColumnLayout {
+    column: Column,
+    x: i32,
+    width: usize,
+}
+
+/// Actions requested by the user that the TUI can't handle by itself.
+pub enum Action {
+    Quit,
+    /// Refresh the devices now, regardless of the refresh period.
+    RefreshDevices,
+    Run(Command),
+}
+
+// Latest data received while the display is paused, shown when it resumes.
+#[derive(Default)]
+struct Pending {
+    devices: Option<LanUserTable>,
+    temperature: Option<((String, String), Option<CmState>)>,
+    modem: Option<(GlobalSettings, CmSystemInfo, DocsisChannels)>,
+}
+
+// Which view fills the screen.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    Devices,
+    Modem,
+    Channels,
+}
+
+impl Screen {
+    fn next(self) -> Self {
+        match self {
+            Screen::Devices => Screen::Modem,
+            Screen::Modem => Screen::Channels,
+            Screen::Channels => Screen::Devices,
+        }
+    }
+}
+
+// A line of the DOCSIS channel tables.
+enum ChannelLine<'a> {
+    Title(&'static str),
+    Header(&'static [(i32, &'static str)]),
+    Downstream(&'a DownstreamChannel),
+    Upstream(&'a UpstreamChannel),
+    Blank,
+}
+
+// Information about the router and its cable modem, refreshed less often than the devices.
+struct ModemStatus {
+    settings: GlobalSettings,
+    system: CmSystemInfo,
+    channels: DocsisChannels,
+    updated: DateTime<Local>,
+}
+
+// What key presses currently apply to.
+enum Mode {
+    Table,
+    // Typing the filter.
+    Filter,
+    // Showing the details of the selected device.
+    Detail,
+    // Typing a new alias for a device.
+    RenameAlias { mac: MacAddr, name: String },
+    // Asking for confirmation before blocking a device.
+    ConfirmBlock(MacAddr),
+    // Showing the key bindings.
+    Help,
+    // Typing a command in the palette.
+    Command(String),
+}
+
+// When a device was seen, and its link speed during its current session.
+struct Seen {
+    first: DateTime<Local>,
+    last: DateTime<Local>,
+    // Start of the current session, unless the device was already connected at the first refresh.
+    joined: Option<DateTime<Local>>,
+    speeds: VecDeque<usize>,
+}
+
+// Outcome of a key press while typing text.
+enum TextInput {
+    Edited,
+    Done,
+    Cancelled,
+    Ignored,
+}
+
+// A row of the device table, with the state of the client at the previous refresh. Devices that
+// joined recently are highlighted, and departed devices linger for a while.
+enum Row {
+    Added(ClientInfo),
+    Removed(ClientInfo),
+    Present { old: ClientInfo, new: ClientInfo },
+}
+
+impl Row {
+    fn client(&self) -> &ClientInfo {
+        match self {
+            Row::Added(client) | Row::Removed(client) => client,
+            Row::Present { new, .. } => new,
+        }
+    }
+}
+
 impl Tui {
-    // Width and position of various fields.
-    const MAC_WIDTH: i32 = 19;
-    const IPV4_WIDTH: i32 = 19;
-    const IPV6_WIDTH: i32 = 41;
-    const SPEED_WIDTH: i32 = 7;
-    const LEASE_WIDTH: i32 = 13;
-
-    const MAC_POS: i32 = 0;
-    const IPV4_POS: i32 = Self::MAC_POS + Self::MAC_WIDTH;
-    const IPV6_POS: i32 = Self::IPV4_POS + Self::IPV4_WIDTH;
-    const SPEED_POS: i32 = Self::IPV6_POS + Self::IPV6_WIDTH;
-    const LEASE_POS: i32 = Self::SPEED_POS + Self::SPEED_WIDTH;
-    const HOSTNAME_POS: i32 = Self::LEASE_POS + Self::LEASE_WIDTH;
-
-    // Indices for ncurses color pairs.
-    const ADDED_PAIR: i16 = 1;
-    const REMOVED_PAIR: i16 = 2;
-    const HIGHLIGHT_PAIR: i16 = 3;
-
-    pub fn new() -> Self {
-        let window = ncurses::initscr();
-        ncurses::cbreak();
-        ncurses::noecho();
-        ncurses::intrflush(window, false);
-        ncurses::curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_INVISIBLE);
-
-        let has_colors = ncurses::has_colors();
-        if has_colors {
-            ncurses::start_color();
-            ncurses::init_pair(
-                Self::ADDED_PAIR,
-                ncurses::constants::COLOR_BLACK,
-                ncurses::constants::COLOR_GREEN,
-            );
-            ncurses::init_pair(
-                Self::REMOVED_PAIR,
-                ncurses::constants::COLOR_WHITE,
-                ncurses::constants::COLOR_RED,
-            );
-            ncurses::init_pair(
-                Self::HIGHLIGHT_PAIR,
-                ncurses::constants::COLOR_BLACK,
-                ncurses::constants::COLOR_WHITE,
-            );
+    // Rows of the router status panel and of the table header. The status bar is on the last row.
+    const PANEL_ROW: i32 = 0;
+    const HEADER_ROW: i32 = 3;
+
+    // Gap between columns, and space kept for the last column before the IPv6 addresses are
+    // truncated.
+    const COLUMN_GAP: usize = 2;
+    const MIN_LAST_COLUMN_WIDTH: usize = 12;
+
+    // Remaining lease time below which a lease is highlighted as about to expire.
+    const LEASE_EXPIRY_WARNING: Duration = Duration::from_secs(5 * 60);
+
+    // Width of the +/-/* row markers of the monochrome theme.
+    const MARKER_WIDTH: usize = 2;
+
+    // Number of rows scrolled by each step of the mouse wheel.
+    const WHEEL_STEP: i32 = 3;
+
+    // Positions and titles of the columns of the DOCSIS channel tables.
+    const DOWNSTREAM_COLUMNS: &'static [(i32, &'static str)] = &[
+        (2, "Ch"),
+        (6, "Frequency"),
+        (18, "Power"),
+        (30, "SNR"),
+        (40, "Modulation"),
+        (52, "Locked"),
+        (64, "Corr/min"),
+        (74, "Uncorr/min"),
+        (86, "Power history"),
+        (112, "SNR history"),
+    ];
+    const UPSTREAM_COLUMNS: &'static [(i32, &'static str)] = &[
+        (2, "Ch"),
+        (6, "Frequency"),
+        (18, "Power"),
+        (30, "Symbol rate"),
+        (44, "Modulation"),
+        (56, "T3"),
+        (62, "T4"),
+        (86, "Power history"),
+    ];
+    // Number of samples shown in each history sparkline.
+    const SPARKLINE_WIDTH: usize = 24;
+
+    // Number of link speed samples kept for each device.
+    const MAX_SPEED_SAMPLES: usize = 120;
+    // Width of the device detail pane, and of the labels in it.
+    const DETAIL_WIDTH: i32 = 72;
+    const DETAIL_LABEL_WIDTH: usize = 15;
+    // Key bindings and commands listed in the help overlay.
+    const HELP: &'static [(&'static str, &'static str)] = &[
+        ("↑↓ / jk", "Select a device, or scroll the channels"),
+        ("PgUp / PgDn", "Scroll by one page"),
+        ("Home/g End/G", "Select the first/last device"),
+        ("< / >", "Sort by the previous/next column"),
+        ("i", "Invert the sort order"),
+        ("6", "Show or hide the IPv6 column"),
+        ("/", "Filter the devices, Esc to clear"),
+        ("Enter", "Show the device details"),
+        ("  c / a / b", "Copy the MAC, rename or block the device"),
+        ("Tab", "Next screen"),
+        ("m / c", "Modem status / DOCSIS channels"),
+        ("p", "Pause or resume the display"),
+        ("r", "Refresh the devices now"),
+        ("s", "Save the devices to a JSON file"),
+        (":", "Command palette:"),
+        ("  block MAC", "Block a device on the router"),
+        ("  reboot", "Reboot the router"),
+        ("  export FMT [PATH]", "Export the devices as json or csv"),
+        ("  refresh SECS", "Change the refresh period of the devices"),
+        ("?", "Show this help"),
+        ("q", "Quit"),
+    ];
+    const HELP_KEY_WIDTH: usize = 20;
+
+    pub fn new(
+        aliases: Aliases,
+        columns: Vec<Column>,
+        linger: Duration,
+        joined_highlight: Duration,
+        theme: Theme,
+    ) -> Self {
+        let terminal = terminal::open();
+        let theme = if terminal.colors() > 0 {
+            theme
+        } else {
+            Theme::Mono
+        };
+        if theme != Theme::Mono {
+            for &style in Style::ALL.iter() {
+                if let Some((fg, bg)) = theme.colors(style, terminal.colors()) {
+                    terminal.init_style(style, fg, bg);
+                }
+            }
         }
 
         Self {
-            window,
+            terminal,
             size: Size { x: 0, y: 0 },
-            clients: Vec::new(),
-            has_colors,
+            columns,
+            layout: Vec::new(),
+            show_ipv6: true,
+            rows: Vec::new(),
+            view: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            sort: Column::Mac,
+            descending: false,
+            filter: String::new(),
+            mode: Mode::Table,
+            aliases,
+            seen: HashMap::new(),
+            linger: chrono::Duration::from_std(linger).unwrap_or_else(|_| chrono::Duration::zero()),
+            joined_highlight: chrono::Duration::from_std(joined_highlight)
+                .unwrap_or_else(|_| chrono::Duration::zero()),
+            screen: Screen::Devices,
+            temperature: ("?".to_owned(), "?".to_owned()),
+            cm_state: None,
+            modem: None,
+            channel_history: ChannelHistory::new(),
+            channel_scroll: 0,
+            message: None,
+            last_refresh: None,
+            table: None,
+            paused: None,
+            theme,
         }
     }
 
     pub fn update(&mut self, table: LanUserTable) {
-        let mut newclients: Vec<ClientInfo> = table.wifi.clientinfo;
-        newclients.sort_by(|x, y| x.mac.cmp(&y.mac));
+        if let Some(pending) = &mut self.paused {
+            pending.devices = Some(table);
+            self.draw();
+            return;
+        }
+        self.table = Some(table.clone());
 
-        ncurses::clear();
-        ncurses::getmaxyx(self.window, &mut self.size.y, &mut self.size.x);
-        if self.has_colors {
-            self.print_clients_diff(&self.clients, &newclients);
-        } else {
-            self.print_clients(&newclients);
+        let mut previous: HashMap<MacAddr, Row> = std::mem::take(&mut self.rows)
+            .into_iter()
+            .map(|row| (row.client().mac, row))
+            .collect();
+
+        let now = Local::now();
+        let first_refresh = self.last_refresh.is_none();
+        let mut rows = Vec::new();
+        for client in table.wifi.clientinfo {
+            let old = match previous.remove(&client.mac) {
+                Some(Row::Added(old)) | Some(Row::Present { new: old, .. }) => Some(old),
+                Some(Row::Removed(_)) | None => None,
+            };
+            let seen = self.seen.entry(client.mac).or_insert_with(|| Seen {
+                first: now,
+                last: now,
+                joined: None,
+                speeds: VecDeque::new(),
+            });
+            // A new session starts when the device reconnects.
+            if old.is_none() {
+                seen.joined = if first_refresh { None } else { Some(now) };
+                seen.speeds.clear();
+            }
+            seen.last = now;
+            seen.speeds.push_back(client.speed);
+            if seen.speeds.len() > Self::MAX_SPEED_SAMPLES {
+                seen.speeds.pop_front();
+            }
+
+            let recently_joined = seen
+                .joined
+                .map_or(false, |joined| now - joined < self.joined_highlight);
+            rows.push(match old {
+                _ if recently_joined => Row::Added(client),
+                Some(old) => Row::Present { old, new: client },
+                None => Row::Present {
+                    old: client.clone(),
+                    new: client,
+                },
+            });
         }
-        ncurses::refresh();
 
-        self.clients = newclients;
+        // Devices that left are kept until the linger period has elapsed.
+        for (mac, row) in previous {
+            if let Some(seen) = self.seen.get(&mac) {
+                if now - seen.last < self.linger {
+                    rows.push(match row {
+                        Row::Added(client)
+                        | Row::Removed(client)
+                        | Row::Present { new: client, .. } => Row::Removed(client),
+                    });
+                }
+            }
+        }
+        rows.sort_by_key(|row| row.client().mac);
+        self.rows = rows;
+
+        self.update_view();
+        self.last_refresh = Some(now);
+        self.draw();
     }
 
-    fn print_clients(&self, clients: &[ClientInfo]) {
-        self.print_header();
-        for (i, c) in clients.iter().enumerate() {
-            self.print_client((i + 1) as i32, c);
+    pub fn update_temperature(&mut self, history: &TemperatureHistory) {
+        let format_stats = |sensor| match history.stats(sensor) {
+            Some(stats) => format!(
+                "{}°C (min {}°C, max {}°C)",
+                stats.current, stats.min, stats.max
+            ),
+            None => "?".to_owned(),
+        };
+        let temperature = (format_stats(Sensor::Board), format_stats(Sensor::Tuner));
+        let cm_state = history.latest().cloned();
+        match &mut self.paused {
+            Some(pending) => pending.temperature = Some((temperature, cm_state)),
+            None => {
+                self.temperature = temperature;
+                self.cm_state = cm_state;
+            }
         }
+        self.draw();
     }
 
-    fn print_clients_diff(&self, oldclients: &[ClientInfo], newclients: &[ClientInfo]) {
-        self.print_header_colored();
+    pub fn update_modem(
+        &mut self,
+        settings: GlobalSettings,
+        system: CmSystemInfo,
+        channels: DocsisChannels,
+    ) {
+        if let Some(pending) = &mut self.paused {
+            pending.modem = Some((settings, system, channels));
+            self.draw();
+            return;
+        }
+        self.channel_history.update(&channels);
+        self.modem = Some(ModemStatus {
+            settings,
+            system,
+            channels,
+            updated: Local::now(),
+        });
+        self.draw();
+    }
 
-        let mut old_it = oldclients.iter().peekable();
-        let mut new_it = newclients.iter().peekable();
-        for i in 1.. {
-            match (old_it.peek(), new_it.peek()) {
-                (Some(o), Some(n)) => match o.mac.cmp(&n.mac) {
-                    Ordering::Equal => {
-                        self.print_client_diff(i, o, n);
-                        new_it.next();
-                        old_it.next();
-                    }
-                    Ordering::Less => {
-                        self.print_removed_client(i, o);
-                        old_it.next();
-                    }
-                    Ordering::Greater => {
-                        self.print_new_client(i, n);
-                        new_it.next();
+    // Freezes the display, or shows the data received while it was frozen.
+    fn toggle_pause(&mut self) {
+        match self.paused.take() {
+            None => self.paused = Some(Pending::default()),
+            Some(pending) => {
+                if let Some(table) = pending.devices {
+                    self.update(table);
+                }
+                if let Some((temperature, cm_state)) = pending.temperature {
+                    self.temperature = temperature;
+                    self.cm_state = cm_state;
+                }
+                if let Some((settings, system, channels)) = pending.modem {
+                    self.update_modem(settings, system, channels);
+                }
+            }
+        }
+    }
+
+    // Saves the displayed devices to a timestamped JSON file in the current directory.
+    fn save_snapshot(&mut self) {
+        let table = match &self.table {
+            Some(table) => table,
+            None => {
+                self.message = Some("No devices to save yet".to_owned());
+                return;
+            }
+        };
+        let path = format!(
+            "connect-box-snapshot-{}.json",
+            self.last_refresh
+                .unwrap_or_else(Local::now)
+                .format("%Y%m%d-%H%M%S")
+        );
+        let result = serde_json::to_string_pretty(table)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            .and_then(|content| std::fs::write(&path, content + "\n"));
+        self.message = Some(match result {
+            Ok(()) => format!("Saved the devices to {}", path),
+            Err(e) => format!("Failed to save the snapshot: {}", e),
+        });
+    }
+
+    /// Shows feedback about an action in the status bar.
+    pub fn show_message(&mut self, message: String) {
+        self.message = Some(message);
+        self.draw();
+    }
+
+    /// Handles the pending key presses, and returns the action requested by the user, if any.
+    pub fn poll_input(&mut self) -> Option<Action> {
+        let mut action = None;
+        let mut redraw = false;
+        while action.is_none() {
+            let key = match self.terminal.poll_key() {
+                Some(key) => key,
+                None => break,
+            };
+            redraw = true;
+            match key {
+                Key::Resize => continue,
+                Key::Interrupt => return Some(Action::Quit),
+                _ => (),
+            }
+            self.message = None;
+            if let Key::Click { .. } | Key::WheelUp | Key::WheelDown = key {
+                self.mouse_event(key);
+                continue;
+            }
+            action = match self.mode {
+                Mode::Help => {
+                    self.mode = Mode::Table;
+                    None
+                }
+                Mode::Command(_) => self.command_key(key),
+                _ if self.screen != Screen::Devices => self.status_screen_key(key),
+                Mode::Table => self.table_key(key),
+                Mode::Filter => {
+                    self.filter_key(key);
+                    None
+                }
+                Mode::Detail => self.detail_key(key),
+                Mode::RenameAlias { .. } => {
+                    self.rename_key(key);
+                    None
+                }
+                Mode::ConfirmBlock(mac) => self.confirm_block_key(key, mac),
+            };
+        }
+
+        if redraw {
+            self.draw();
+        }
+        action
+    }
+
+    // Sorts by the clicked column, selects the clicked row, or scrolls with the wheel.
+    fn mouse_event(&mut self, key: Key) {
+        let wheel = match key {
+            Key::WheelUp => -Self::WHEEL_STEP,
+            Key::WheelDown => Self::WHEEL_STEP,
+            _ => 0,
+        };
+
+        match self.mode {
+            Mode::Help => {
+                self.mode = Mode::Table;
+                return;
+            }
+            Mode::Table => (),
+            _ => return,
+        }
+        if self.screen != Screen::Devices {
+            self.channel_scroll = (self.channel_scroll as i64 + wheel as i64).max(0) as usize;
+            return;
+        }
+        if wheel != 0 {
+            self.move_selection(wheel);
+            return;
+        }
+        let (x, y) = match key {
+            Key::Click { x, y } => (x, y),
+            _ => return,
+        };
+
+        if y == Self::HEADER_ROW {
+            let clicked = self.layout.iter().find(|layout| {
+                x >= layout.x && x < layout.x + (layout.width + Self::COLUMN_GAP) as i32
+            });
+            if let Some(layout) = clicked {
+                // Clicking the sorted column inverts the order.
+                if self.sort == layout.column {
+                    self.descending = !self.descending;
+                } else {
+                    self.sort = layout.column;
+                }
+                self.update_view();
+            }
+        } else if y > Self::HEADER_ROW && y <= Self::HEADER_ROW + self.page_height() {
+            let index = self.scroll + (y - Self::HEADER_ROW - 1) as usize;
+            if index < self.view.len() {
+                // Clicking the selected row shows its details.
+                if index == self.selected {
+                    self.mode = Mode::Detail;
+                }
+                self.selected = index;
+            }
+        }
+    }
+
+    fn table_key(&mut self, key: Key) -> Option<Action> {
+        if self.navigation_key(key) || self.screen_key(key) {
+            return None;
+        }
+        match key {
+            Key::Escape => {
+                self.filter.clear();
+                self.update_view();
+            }
+            Key::Enter if !self.view.is_empty() => self.mode = Mode::Detail,
+            Key::Char(c) => match c {
+                '<' | '>' => {
+                    self.sort = self.next_sort_column(c == '>');
+                    self.update_view();
+                }
+                'i' => {
+                    self.descending = !self.descending;
+                    self.update_view();
+                }
+                '/' => self.mode = Mode::Filter,
+                '6' => self.show_ipv6 = !self.show_ipv6,
+                'p' => self.toggle_pause(),
+                'r' => return Some(Action::RefreshDevices),
+                's' => self.save_snapshot(),
+                'q' => return Some(Action::Quit),
+                _ => (),
+            },
+            _ => (),
+        }
+        None
+    }
+
+    // Handles a key press in the modem and channel screens.
+    fn status_screen_key(&mut self, key: Key) -> Option<Action> {
+        if self.screen_key(key) {
+            return None;
+        }
+        let page = self.page_height() as usize;
+        match key {
+            Key::Escape => self.screen = Screen::Devices,
+            Key::Up => self.channel_scroll = self.channel_scroll.saturating_sub(1),
+            Key::Down => self.channel_scroll += 1,
+            Key::PageUp => self.channel_scroll = self.channel_scroll.saturating_sub(page),
+            Key::PageDown => self.channel_scroll += page,
+            Key::Char(c) => match c {
+                'k' => self.channel_scroll = self.channel_scroll.saturating_sub(1),
+                'j' => self.channel_scroll += 1,
+                'p' => self.toggle_pause(),
+                'r' => return Some(Action::RefreshDevices),
+                's' => self.save_snapshot(),
+                'q' => return Some(Action::Quit),
+                _ => (),
+            },
+            _ => (),
+        }
+        None
+    }
+
+    // Switches between screens, or opens the help or the command palette, and returns whether the
+    // key was handled.
+    fn screen_key(&mut self, key: Key) -> bool {
+        let current = self.screen;
+        let toggle = |screen| {
+            if current == screen {
+                Screen::Devices
+            } else {
+                screen
+            }
+        };
+        self.screen = match key {
+            Key::Tab => current.next(),
+            Key::Char(c) => match c {
+                'm' => toggle(Screen::Modem),
+                'c' => toggle(Screen::Channels),
+                '?' => {
+                    self.mode = Mode::Help;
+                    current
+                }
+                ':' => {
+                    self.mode = Mode::Command(String::new());
+                    current
+                }
+                _ => return false,
+            },
+            _ => return false,
+        };
+        true
+    }
+
+    fn command_key(&mut self, key: Key) -> Option<Action> {
+        let text = match &mut self.mode {
+            Mode::Command(text) => text,
+            _ => return None,
+        };
+        match edit_text(text, key) {
+            TextInput::Done => {
+                let result = text.parse();
+                self.mode = Mode::Table;
+                match result {
+                    Ok(command) => return Some(Action::Run(command)),
+                    Err(e) => self.message = Some(e),
+                }
+            }
+            TextInput::Cancelled => self.mode = Mode::Table,
+            TextInput::Edited | TextInput::Ignored => (),
+        }
+        None
+    }
+
+    // Moves the selection, and returns whether the key was a navigation key.
+    fn navigation_key(&mut self, key: Key) -> bool {
+        match key {
+            Key::Up => self.move_selection(-1),
+            Key::Down => self.move_selection(1),
+            Key::PageUp => self.move_selection(-self.page_height()),
+            Key::PageDown => self.move_selection(self.page_height()),
+            Key::Home => self.selected = 0,
+            Key::End => self.selected = self.view.len().saturating_sub(1),
+            Key::Char(c) => match c {
+                'k' => self.move_selection(-1),
+                'j' => self.move_selection(1),
+                'g' => self.selected = 0,
+                'G' => self.selected = self.view.len().saturating_sub(1),
+                _ => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    // Handles a key press while the filter is being typed. The table is filtered as the user
+    // types.
+    fn filter_key(&mut self, key: Key) {
+        match edit_text(&mut self.filter, key) {
+            TextInput::Edited => self.update_view(),
+            TextInput::Done => self.mode = Mode::Table,
+            TextInput::Cancelled => {
+                self.filter.clear();
+                self.update_view();
+                self.mode = Mode::Table;
+            }
+            TextInput::Ignored => (),
+        }
+    }
+
+    fn detail_key(&mut self, key: Key) -> Option<Action> {
+        if self.navigation_key(key) {
+            return None;
+        }
+        let mac = match self.selected_row() {
+            Some(row) => row.client().mac,
+            None => {
+                self.mode = Mode::Table;
+                return None;
+            }
+        };
+        match key {
+            Key::Escape | Key::Enter => self.mode = Mode::Table,
+            Key::Char(c) => match c {
+                'c' => {
+                    self.message = Some(match copy_to_clipboard(&mac.to_string()) {
+                        Ok(()) => format!("Copied {} to the clipboard", mac),
+                        Err(e) => format!("Failed to copy to the clipboard: {}", e),
+                    })
+                }
+                'a' => {
+                    self.mode = Mode::RenameAlias {
+                        mac,
+                        name: self.aliases.get(&mac).unwrap_or_default().to_owned(),
                     }
-                },
-                (None, Some(n)) => {
-                    self.print_new_client(i, n);
-                    new_it.next();
                 }
-                (Some(o), None) => {
-                    self.print_removed_client(i, o);
-                    old_it.next();
+                'b' => self.mode = Mode::ConfirmBlock(mac),
+                'q' => self.mode = Mode::Table,
+                _ => (),
+            },
+            _ => (),
+        }
+        None
+    }
+
+    fn rename_key(&mut self, key: Key) {
+        let (mac, name) = match &mut self.mode {
+            Mode::RenameAlias { mac, name } => (*mac, name),
+            _ => return,
+        };
+        match edit_text(name, key) {
+            TextInput::Done => {
+                let name = std::mem::take(name);
+                self.mode = Mode::Detail;
+                match self.aliases.set(mac, &name) {
+                    Ok(()) => self.update_view(),
+                    Err(e) => self.message = Some(format!("Failed to save the alias: {}", e)),
                 }
-                (None, None) => break,
             }
+            TextInput::Cancelled => self.mode = Mode::Detail,
+            TextInput::Edited | TextInput::Ignored => (),
         }
     }
 
-    fn print_removed_client(&self, i: i32, client: &ClientInfo) {
-        ncurses::attron(ncurses::COLOR_PAIR(Self::REMOVED_PAIR));
-        self.print_client(i, client);
-        ncurses::attroff(ncurses::COLOR_PAIR(Self::REMOVED_PAIR));
+    fn confirm_block_key(&mut self, key: Key, mac: MacAddr) -> Option<Action> {
+        self.mode = Mode::Detail;
+        if key == Key::Char('y') {
+            Some(Action::Run(Command::Block(mac)))
+        } else {
+            self.message = Some(format!("Not blocking {}", mac));
+            None
+        }
     }
 
-    fn print_new_client(&self, i: i32, client: &ClientInfo) {
-        ncurses::attron(ncurses::COLOR_PAIR(Self::ADDED_PAIR));
-        self.print_client(i, client);
-        ncurses::attroff(ncurses::COLOR_PAIR(Self::ADDED_PAIR));
+    fn selected_row(&self) -> Option<&Row> {
+        self.view.get(self.selected).map(|&i| &self.rows[i])
     }
 
-    fn print_client_diff(&self, i: i32, oldclient: &ClientInfo, newclient: &ClientInfo) {
-        self.addstr_at(i, Self::MAC_POS, &newclient.mac);
-        self.addstr_at_option_diff(
-            i,
-            Self::IPV4_POS,
-            oldclient.ipv4.as_ref(),
-            newclient.ipv4.as_ref(),
+    // Recomputes the displayed rows after a change of the rows, filter or sort order, keeping the
+    // selection on the same device.
+    fn update_view(&mut self) {
+        let selected_mac = self.selected_row().map(|row| row.client().mac);
+
+        let filter = self.filter.to_lowercase();
+        let rows = &self.rows;
+        let aliases = &self.aliases;
+        let mut view: Vec<usize> = (0..rows.len())
+            .filter(|&i| matches_filter(rows[i].client(), aliases, &filter))
+            .collect();
+        // The rows are sorted by MAC address, which the stable sort keeps as a tie-breaker.
+        view.sort_by(|&i, &j| {
+            let ordering = self.compare(self.sort, &rows[i], &rows[j]);
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        self.view = view;
+
+        if let Some(i) = selected_mac.and_then(|mac| {
+            self.view
+                .iter()
+                .position(|&i| self.rows[i].client().mac == mac)
+        }) {
+            self.selected = i;
+        }
+    }
+
+    fn move_selection(&mut self, delta: i32) {
+        let last = self.view.len().saturating_sub(1) as i64;
+        self.selected = (self.selected as i64 + delta as i64).clamp(0, last.max(0)) as usize;
+    }
+
+    // Number of table rows that fit on the screen, between the header and the status bar.
+    fn page_height(&self) -> i32 {
+        (self.size.y - Self::HEADER_ROW - 2).max(1)
+    }
+
+    fn draw(&mut self) {
+        // Only the characters that changed since the last draw are sent to the terminal.
+        self.terminal.erase();
+        let (y, x) = self.terminal.size();
+        self.size = Size { x, y };
+        match self.screen {
+            Screen::Devices => self.draw_devices(),
+            Screen::Modem => self.print_modem(),
+            Screen::Channels => self.print_channels(),
+        }
+        if let Mode::Help = self.mode {
+            self.print_help();
+        }
+        self.print_status_bar();
+        self.terminal.refresh();
+    }
+
+    fn draw_devices(&mut self) {
+        // Scroll just enough for the selected row to be visible.
+        let height = self.page_height() as usize;
+        self.selected = self.selected.min(self.view.len().saturating_sub(1));
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+        self.scroll = self.scroll.min(self.view.len().saturating_sub(height));
+
+        self.update_layout();
+        self.print_panel();
+        self.print_header_styled();
+        for (i, &index) in self.view.iter().enumerate().skip(self.scroll).take(height) {
+            let y = Self::HEADER_ROW + 1 + (i - self.scroll) as i32;
+            let row = &self.rows[index];
+            if i == self.selected {
+                self.terminal.attr_on(Attributes::REVERSE);
+                self.fill_row(y);
+                self.print_row(y, row);
+                self.terminal.attr_off(Attributes::REVERSE);
+            } else {
+                self.print_row(y, row);
+            }
+        }
+        if let Mode::Detail | Mode::RenameAlias { .. } | Mode::ConfirmBlock(_) = self.mode {
+            self.print_detail();
+        }
+    }
+
+    // Columns of the device table that are currently shown.
+    fn visible_columns(&self) -> Vec<Column> {
+        self.columns
+            .iter()
+            .copied()
+            .filter(|&column| column != Column::Ipv6 || self.show_ipv6)
+            .collect()
+    }
+
+    // Cycles through the displayed columns to sort by.
+    fn next_sort_column(&self, forward: bool) -> Column {
+        let columns = self.visible_columns();
+        let len = columns.len();
+        match columns.iter().position(|&column| column == self.sort) {
+            Some(i) if forward => columns[(i + 1) % len],
+            Some(i) => columns[(i + len - 1) % len],
+            None => columns[0],
+        }
+    }
+
+    // Compares two rows by a column. Missing values sort first.
+    fn compare(&self, column: Column, a: &Row, b: &Row) -> Ordering {
+        let (ca, cb) = (a.client(), b.client());
+        let seen = |row: &Row| self.seen.get(&row.client().mac);
+        match column {
+            Column::Mac => ca.mac.cmp(&cb.mac),
+            Column::Ipv4 => ca.ipv4.cmp(&cb.ipv4),
+            Column::Ipv6 => ca.ipv6.cmp(&cb.ipv6),
+            Column::Speed => ca.speed.cmp(&cb.speed),
+            Column::Lease => ca.lease_time.cmp(&cb.lease_time),
+            Column::Hostname => ca.hostname.to_lowercase().cmp(&cb.hostname.to_lowercase()),
+            Column::Vendor => vendor::lookup(&ca.mac).cmp(&vendor::lookup(&cb.mac)),
+            Column::Alias => self
+                .aliases
+                .get(&ca.mac)
+                .map(str::to_lowercase)
+                .cmp(&self.aliases.get(&cb.mac).map(str::to_lowercase)),
+            Column::Interface => {
+                (ca.interface_kind(), &ca.interface).cmp(&(cb.interface_kind(), &cb.interface))
+            }
+            Column::Method => ca.method.cmp(&cb.method),
+            Column::FirstSeen => seen(a).map(|s| s.first).cmp(&seen(b).map(|s| s.first)),
+            // Online devices sort first, then the devices that left most recently.
+            Column::Offline => {
+                let offline = |row: &Row| match row {
+                    Row::Removed(_) => seen(row).map(|s| std::cmp::Reverse(s.last)),
+                    _ => None,
+                };
+                offline(a).cmp(&offline(b))
+            }
+        }
+    }
+
+    // Text of a cell of the device table.
+    fn cell(&self, column: Column, client: &ClientInfo, online: bool) -> String {
+        let seen = self.seen.get(&client.mac);
+        match column {
+            Column::Mac => client.mac.to_string(),
+            Column::Ipv4 => client.ipv4.map_or_else(String::new, |ip| ip.to_string()),
+            Column::Ipv6 => client.ipv6.map_or_else(String::new, |ip| ip.to_string()),
+            Column::Speed => client.speed.to_string(),
+            Column::Lease if online => format_lease_expiry(client.lease_time),
+            Column::Lease => seen.map_or_else(String::new, |s| {
+                format!("gone {} ago", format_elapsed(&s.last))
+            }),
+            Column::Hostname => client.hostname.clone(),
+            Column::Vendor => vendor::lookup(&client.mac).unwrap_or("").to_owned(),
+            Column::Alias => self.aliases.get(&client.mac).unwrap_or("").to_owned(),
+            Column::Interface => match client.interface_kind() {
+                InterfaceKind::Other(_) => client.interface.clone(),
+                kind => kind.to_string(),
+            },
+            Column::Method => client.method.to_string(),
+            Column::FirstSeen => seen.map_or_else(String::new, |s| format_time(&s.first)),
+            Column::Offline if online => String::new(),
+            Column::Offline => seen.map_or_else(String::new, |s| format_elapsed(&s.last)),
+        }
+    }
+
+    // Fits the columns of the device table to their content and to the width of the terminal.
+    // The last column takes the remaining space, and IPv6 addresses are truncated to make room
+    // for it.
+    fn update_layout(&mut self) {
+        let columns = self.visible_columns();
+        let mut widths: Vec<usize> = columns
+            .iter()
+            .map(|&column| {
+                // Leave room for the sort arrow in the header.
+                let title = text::width(column.title()) + 1;
+                let width = |client, online| text::width(&self.cell(column, client, online));
+                self.rows
+                    .iter()
+                    .map(|row| match row {
+                        Row::Added(client) => width(client, true),
+                        Row::Removed(client) => width(client, false),
+                        // Addresses that disappeared are shown until the next refresh.
+                        Row::Present { old, new } => width(old, true).max(width(new, true)),
+                    })
+                    .fold(title, usize::max)
+            })
+            .collect();
+
+        let total: usize = widths
+            .iter()
+            .map(|width| width + Self::COLUMN_GAP)
+            .sum::<usize>()
+            - widths.last().copied().unwrap_or(0)
+            + Self::MIN_LAST_COLUMN_WIDTH;
+        // The monochrome theme shows row markers before the first column.
+        let start = if self.theme == Theme::Mono {
+            Self::MARKER_WIDTH
+        } else {
+            0
+        };
+        let excess = (start + total).saturating_sub(self.size.x.max(0) as usize);
+        if let Some(i) = columns.iter().position(|&column| column == Column::Ipv6) {
+            if i + 1 < columns.len() {
+                let min = text::width(Column::Ipv6.title()) + 1;
+                widths[i] = widths[i].saturating_sub(excess).max(min);
+            }
+        }
+
+        let mut x = start;
+        self.layout = columns
+            .into_iter()
+            .zip(widths)
+            .map(|(column, width)| {
+                let layout = ColumnLayout {
+                    column,
+                    x: x as i32,
+                    width,
+                };
+                x += width + Self::COLUMN_GAP;
+                layout
+            })
+            .collect();
+    }
+
+    // Summarizes the status of the router above the device table.
+    fn print_panel(&self) {
+        let (state, wan) = match &self.cm_state {
+            Some(state) => (state.oper_state.as_str(), format_wan_addresses(state)),
+            None => ("?", "?".to_owned()),
+        };
+        let (router, docsis) = match &self.modem {
+            Some(modem) => (
+                format!(
+                    "{} {}  |  Up {}",
+                    modem.settings.model,
+                    modem.settings.sw_version,
+                    format_uptime(modem.system.uptime)
+                ),
+                format!(
+                    "{}: {}",
+                    modem.system.docsis_mode,
+                    lock_summary(&modem.channels)
+                ),
+            ),
+            None => ("Router: ?".to_owned(), "DOCSIS: ?".to_owned()),
+        };
+
+        self.addstr_at(
+            Self::PANEL_ROW,
+            0,
+            &format!("{}  |  {}  |  WAN {}", router, state, wan),
         );
-        self.addstr_at_option_diff(
-            i,
-            Self::IPV6_POS,
-            oldclient.ipv6.as_ref(),
-            newclient.ipv6.as_ref(),
+        self.addstr_at(Self::PANEL_ROW + 1, 0, &docsis);
+        self.addstr_at(
+            Self::PANEL_ROW + 2,
+            0,
+            &format!(
+                "Temperature: {}  Tuner: {}",
+                self.temperature.0, self.temperature.1
+            ),
         );
+    }
+
+    // Shows all the information about the router and its cable modem.
+    fn print_modem(&self) {
+        let unknown = || "?".to_owned();
+        let mut lines: Vec<(&str, String)> = Vec::new();
+
+        push_section(&mut lines, "Router");
+        match &self.modem {
+            Some(modem) => {
+                let (settings, system) = (&modem.settings, &modem.system);
+                lines.push(("Model", settings.model.clone()));
+                lines.push(("Firmware", settings.sw_version.clone()));
+                lines.push(("Hardware", system.hardware_version.clone()));
+                lines.push(("Serial number", system.serial_number.clone()));
+                lines.push(("Modem MAC", system.mac.clone()));
+                lines.push(("Uptime", format_uptime(system.uptime)));
+                lines.push(("Operator", settings.operator_id.clone()));
+                lines.push((
+                    "Provisioning",
+                    format!(
+                        "modem {}, gateway {}",
+                        settings.cm_provision_mode, settings.gw_provision_mode
+                    ),
+                ));
+                lines.push(("Network access", system.network_access.clone()));
+            }
+            None => lines.push(("Status", "waiting for the router".to_owned())),
+        }
+
+        push_section(&mut lines, "WAN");
+        match &self.cm_state {
+            Some(state) => {
+                lines.push(("State", state.oper_state.clone()));
+                lines.push((
+                    "IPv4",
+                    state
+                        .wan_ipv4_addr
+                        .map_or_else(unknown, |ip| ip.to_string()),
+                ));
+                for addr in &state.wan_ipv6_addr.addresses {
+                    lines.push(("IPv6", addr.clone()));
+                }
+            }
+            None => lines.push(("State", unknown())),
+        }
+
+        push_section(&mut lines, "Temperature");
+        lines.push(("Board", self.temperature.0.clone()));
+        lines.push(("Tuner", self.temperature.1.clone()));
+
+        push_section(&mut lines, "DOCSIS");
+        match &self.modem {
+            Some(modem) => {
+                let channels = &modem.channels;
+                let count = |locked: fn(&DownstreamChannel) -> bool| {
+                    channels.downstream.iter().filter(|c| locked(c)).count()
+                };
+                lines.push(("Mode", modem.system.docsis_mode.clone()));
+                lines.push((
+                    "Downstream",
+                    format!(
+                        "{} channels: {} QAM locked, {} FEC locked, {} MPEG locked",
+                        channels.downstream.len(),
+                        count(|c| c.qam_locked),
+                        count(|c| c.fec_locked),
+                        count(|c| c.mpeg_locked)
+                    ),
+                ));
+                lines.push((
+                    "Upstream",
+                    format!(
+                        "{} channels, {} T3 timeouts, {} T4 timeouts",
+                        channels.upstream.len(),
+                        channels.upstream.iter().map(|c| c.t3_timeouts).sum::<u64>(),
+                        channels.upstream.iter().map(|c| c.t4_timeouts).sum::<u64>()
+                    ),
+                ));
+                lines.push(("Updated", modem.updated.format("%H:%M:%S").to_string()));
+            }
+            None => lines.push(("Mode", unknown())),
+        }
+
+        for (i, (label, value)) in lines.iter().enumerate() {
+            let y = Self::PANEL_ROW + i as i32;
+            if value.is_empty() {
+                self.terminal.attr_on(Attributes::BOLD);
+                self.addstr_at(y, 0, label);
+                self.terminal.attr_off(Attributes::BOLD);
+            } else {
+                self.addstr_at(
+                    y,
+                    2,
+                    &format!(
+                        "{:width$}{}",
+                        label,
+                        value,
+                        width = Self::DETAIL_LABEL_WIDTH
+                    ),
+                );
+            }
+        }
+    }
 
-        let raw_speed = format!("{:5}", newclient.speed);
-        let trimmed_speed = raw_speed.trim_start();
-        let trimmed_count = (raw_speed.len() - trimmed_speed.len()) as i32;
-        self.addstr_at_cmp(
-            i,
-            Self::SPEED_POS + trimmed_count,
-            &trimmed_speed,
-            newclient.speed.cmp(&oldclient.speed),
+    // Tables the downstream and upstream channels, with the quality of their signal and its recent
+    // history.
+    fn print_channels(&mut self) {
+        let channels = match &self.modem {
+            Some(modem) => &modem.channels,
+            None => {
+                self.addstr_at(Self::PANEL_ROW, 0, "Waiting for the router...");
+                return;
+            }
+        };
+
+        let mut lines = vec![
+            ChannelLine::Title("Downstream"),
+            ChannelLine::Header(Self::DOWNSTREAM_COLUMNS),
+        ];
+        lines.extend(channels.downstream.iter().map(ChannelLine::Downstream));
+        lines.push(ChannelLine::Blank);
+        lines.push(ChannelLine::Title("Upstream"));
+        lines.push(ChannelLine::Header(Self::UPSTREAM_COLUMNS));
+        lines.extend(channels.upstream.iter().map(ChannelLine::Upstream));
+
+        let height = (self.size.y - 1).max(0) as usize;
+        let scroll = self.channel_scroll.min(lines.len().saturating_sub(height));
+        for (i, line) in lines.iter().skip(scroll).take(height).enumerate() {
+            let y = Self::PANEL_ROW + i as i32;
+            match line {
+                ChannelLine::Title(title) => {
+                    self.terminal.attr_on(Attributes::BOLD);
+                    self.addstr_at(y, 0, title);
+                    self.terminal.attr_off(Attributes::BOLD);
+                }
+                ChannelLine::Header(columns) => {
+                    self.terminal.attr_on(Attributes::UNDERLINE);
+                    for &(x, title) in columns.iter() {
+                        self.addstr_at(y, x, title);
+                    }
+                    self.terminal.attr_off(Attributes::UNDERLINE);
+                }
+                ChannelLine::Downstream(channel) => self.print_downstream_channel(y, channel),
+                ChannelLine::Upstream(channel) => self.print_upstream_channel(y, channel),
+                ChannelLine::Blank => (),
+            }
+        }
+        self.channel_scroll = scroll;
+    }
+
+    fn print_downstream_channel(&self, y: i32, channel: &DownstreamChannel) {
+        let x = |i: usize| Self::DOWNSTREAM_COLUMNS[i].0;
+        let history = self.channel_history.downstream(channel.id);
+
+        self.addstr_at(y, x(0), &format!("{:>2}", channel.id));
+        self.addstr_at(y, x(1), &format_frequency(channel.frequency));
+        self.addstr_at_quality(
+            y,
+            x(2),
+            &format!("{:.1} dBmV", channel.power),
+            docsis::downstream_power_quality(channel.power),
+        );
+        self.addstr_at_quality(
+            y,
+            x(3),
+            &format!("{:.1} dB", channel.snr),
+            docsis::downstream_snr_quality(channel),
         );
+        self.addstr_at(y, x(4), &channel.modulation);
+
+        let unlocked: Vec<&str> = [
+            (channel.qam_locked, "QAM"),
+            (channel.fec_locked, "FEC"),
+            (channel.mpeg_locked, "MPEG"),
+        ]
+        .iter()
+        .filter(|(locked, _)| !locked)
+        .map(|&(_, name)| name)
+        .collect();
+        if unlocked.is_empty() {
+            self.addstr_at_quality(y, x(5), "yes", Quality::Good);
+        } else {
+            self.addstr_at_quality(y, x(5), &format!("no {}", unlocked.join(",")), Quality::Bad);
+        }
+
+        if let Some(history) = history {
+            if let Some((corrected, uncorrectable)) = history.error_rates {
+                self.addstr_at(y, x(6), &format!("{:>8.1}", corrected));
+                let quality = if uncorrectable > 0.0 {
+                    Quality::Bad
+                } else {
+                    Quality::Good
+                };
+                self.addstr_at_quality(y, x(7), &format!("{:>10.1}", uncorrectable), quality);
+            }
+            self.addstr_at(y, x(8), &recent_sparkline(&history.power, -15.0, 15.0));
+            self.addstr_at(y, x(9), &recent_sparkline(&history.snr, 20.0, 45.0));
+        }
+    }
+
+    fn print_upstream_channel(&self, y: i32, channel: &UpstreamChannel) {
+        let x = |i: usize| Self::UPSTREAM_COLUMNS[i].0;
 
-        self.addstr_at_diff(
-            i,
-            Self::LEASE_POS,
-            &oldclient.lease_time,
-            &newclient.lease_time,
+        self.addstr_at(y, x(0), &format!("{:>2}", channel.id));
+        self.addstr_at(y, x(1), &format_frequency(channel.frequency));
+        self.addstr_at_quality(
+            y,
+            x(2),
+            &format!("{:.1} dBmV", channel.power),
+            docsis::upstream_power_quality(channel.power),
         );
-        self.addstr_at_diff(
-            i,
-            Self::HOSTNAME_POS,
-            &oldclient.hostname,
-            &newclient.hostname,
+        self.addstr_at(y, x(3), &format!("{} ksym/s", channel.symbol_rate));
+        self.addstr_at(y, x(4), &channel.modulation);
+        for &(i, timeouts) in [(5, channel.t3_timeouts), (6, channel.t4_timeouts)].iter() {
+            let quality = if timeouts > 0 {
+                Quality::Marginal
+            } else {
+                Quality::Good
+            };
+            self.addstr_at_quality(y, x(i), &timeouts.to_string(), quality);
+        }
+
+        if let Some(history) = self.channel_history.upstream(channel.id) {
+            self.addstr_at(y, x(7), &recent_sparkline(&history.power, 30.0, 55.0));
+        }
+    }
+
+    fn print_status_bar(&self) {
+        let status = if let Mode::Command(text) = &self.mode {
+            format!(" :{}_  |  Enter: run  Esc: cancel", text)
+        } else if let Mode::Help = self.mode {
+            " Help".to_owned()
+        } else if let (Some(message), false) = (&self.message, self.screen == Screen::Devices) {
+            format!(" {}", message)
+        } else if self.screen == Screen::Modem {
+            " Modem status  |  Tab: next screen  m/Esc: devices  c: channels  ?: help  q: quit"
+                .to_owned()
+        } else if self.screen == Screen::Channels {
+            let mut updated = match &self.modem {
+                Some(modem) => format!("updated at {}", modem.updated.format("%H:%M:%S")),
+                None => "waiting for the router".to_owned(),
+            };
+            if self.paused.is_some() {
+                updated = format!("PAUSED (p to resume), {}", updated);
+            }
+            format!(
+                " DOCSIS channels, {}  |  ↑↓/jk: scroll  Tab: next screen  c/Esc: devices  m: modem  ?: help  q: quit",
+                updated
+            )
+        } else if let Mode::Filter = self.mode {
+            format!(" /{}_  |  Enter: apply  Esc: clear", self.filter)
+        } else if let Mode::RenameAlias { mac, name } = &self.mode {
+            format!(
+                " Alias of {}: {}_  |  Enter: save (empty to remove)  Esc: cancel",
+                mac, name
+            )
+        } else if let Mode::ConfirmBlock(mac) = self.mode {
+            format!(" Block {} on the router? (y/n)", mac)
+        } else if let Some(message) = &self.message {
+            format!(" {}", message)
+        } else {
+            let mut position = if self.view.is_empty() {
+                "No devices".to_owned()
+            } else {
+                format!("Device {}/{}", self.selected + 1, self.view.len())
+            };
+            if self.view.len() != self.rows.len() {
+                position += &format!(" ({} total)", self.rows.len());
+            }
+            let mut refreshed = match &self.last_refresh {
+                Some(time) => format!("updated at {}", time.format("%H:%M:%S")),
+                None => "waiting for the router".to_owned(),
+            };
+            if self.paused.is_some() {
+                refreshed = format!("PAUSED (p to resume), {}", refreshed);
+            }
+            let filter = if self.filter.is_empty() {
+                String::new()
+            } else {
+                format!(", filter {:?}", self.filter)
+            };
+            format!(
+                " {}, {}, by {} {}{}  |  ↑↓/jk: select  PgUp/PgDn: scroll  Enter: details  </>: sort  i: invert  /: filter  ?: help  q: quit",
+                position,
+                refreshed,
+                self.sort.title(),
+                self.sort_arrow(),
+                filter
+            )
+        };
+
+        let y = self.size.y - 1;
+        self.terminal.attr_on(Attributes::REVERSE);
+        self.fill_row(y);
+        self.addstr_at(y, 0, &status);
+        self.terminal.attr_off(Attributes::REVERSE);
+    }
+
+    // Draws the details of the selected device in a box over the table.
+    fn print_detail(&self) {
+        let row = match self.selected_row() {
+            Some(row) => row,
+            None => return,
+        };
+        let client = row.client();
+        let seen = self.seen.get(&client.mac);
+        let format_seen = |time: &DateTime<Local>| time.format("%Y-%m-%d %H:%M:%S").to_string();
+        let none = || "-".to_owned();
+
+        let width = Self::DETAIL_WIDTH.min(self.size.x);
+        let value_width = (width as usize).saturating_sub(Self::DETAIL_LABEL_WIDTH + 4);
+        let speeds = seen.map_or_else(Vec::new, |seen| {
+            let skip = seen.speeds.len().saturating_sub(value_width);
+            seen.speeds.iter().skip(skip).map(|&s| s as f64).collect()
+        });
+        let max_speed = speeds.iter().cloned().fold(0.0, f64::max);
+
+        let lines = [
+            (
+                "Status",
+                match (row, seen) {
+                    (
+                        Row::Added(_),
+                        Some(Seen {
+                            joined: Some(joined),
+                            ..
+                        }),
+                    ) => format!("joined {} ago", format_elapsed(joined)),
+                    (Row::Removed(_), Some(seen)) => {
+                        format!("disconnected {} ago", format_elapsed(&seen.last))
+                    }
+                    (Row::Removed(_), None) => "disconnected".to_owned(),
+                    _ => "connected".to_owned(),
+                },
+            ),
+            ("MAC", client.mac.to_string()),
+            (
+                "Alias",
+                self.aliases
+                    .get(&client.mac)
+                    .map_or_else(none, str::to_owned),
+            ),
+            ("Hostname", client.hostname.clone()),
+            (
+                "Vendor",
+                match vendor::lookup(&client.mac) {
+                    Some(vendor) => vendor.to_owned(),
+                    None if client.mac.is_locally_administered() => "(randomized MAC)".to_owned(),
+                    None => none(),
+                },
+            ),
+            ("Index", client.index.to_string()),
+            ("IPv4", client.ipv4.map_or_else(none, |ip| ip.to_string())),
+            ("IPv6", client.ipv6.map_or_else(none, |ip| ip.to_string())),
+            (
+                "Interface",
+                match client.interface_kind() {
+                    InterfaceKind::Other(id) => format!("{} (#{})", client.interface, id),
+                    kind => format!("{} ({}, #{})", client.interface, kind, client.interfaceid),
+                },
+            ),
+            ("Method", client.method.to_string()),
+            (
+                "Lease",
+                format!(
+                    "{} (until {})",
+                    format_lease_time(client.lease_time),
+                    format_lease_expiry(client.lease_time)
+                ),
+            ),
+            ("Speed", client.speed.to_string()),
+            ("Speed history", sparkline(&speeds, 0.0, max_speed)),
+            (
+                "First seen",
+                seen.map_or_else(none, |s| format_seen(&s.first)),
+            ),
+            (
+                "Last seen",
+                seen.map_or_else(none, |s| format_seen(&s.last)),
+            ),
+        ];
+
+        let height = lines.len() as i32 + 4;
+        let top = Self::HEADER_ROW + 1;
+        let left = (self.size.x - width) / 2;
+        for y in top..top + height {
+            self.addstr_at(y, left, &" ".repeat(width.max(0) as usize));
+        }
+        self.terminal.draw_box(top, left, height, width);
+        self.addstr_at(top, left + 2, " Device details ");
+        for (i, (label, value)) in lines.iter().enumerate() {
+            let line = format!(
+                "{:width$}{}",
+                label,
+                truncate(value, value_width),
+                width = Self::DETAIL_LABEL_WIDTH
+            );
+            self.addstr_at(top + 1 + i as i32, left + 2, &line);
+        }
+        self.addstr_at(
+            top + height - 2,
+            left + 2,
+            "c: copy MAC  a: rename  b: block  Esc: close",
         );
     }
 
-    fn print_header_colored(&self) {
-        ncurses::attron(ncurses::A_BOLD());
-        ncurses::attron(ncurses::A_STANDOUT());
-        ncurses::wmove(self.window, 0, 0);
-        for _ in 0..self.size.x {
-            // Note: There is only a direct char -> u32 conversion, but ncurses::chtype can either
-            // be u32 or u64 depending on the platform, therefore we need an extra .into() at the
-            // moment.
-            ncurses::waddch(self.window, (' ' as u32).into());
+    // Lists the key bindings and commands in a box over the current screen.
+    fn print_help(&self) {
+        let width = (Self::HELP
+            .iter()
+            .map(|(_, description)| text::width(description))
+            .max()
+            .unwrap_or(0)
+            + Self::HELP_KEY_WIDTH
+            + 4) as i32;
+        let width = width.min(self.size.x);
+        let height = Self::HELP.len() as i32 + 4;
+        let top = ((self.size.y - 1 - height) / 2).max(0);
+        let left = ((self.size.x - width) / 2).max(0);
+        for y in top..top + height {
+            self.addstr_at(y, left, &" ".repeat(width.max(0) as usize));
         }
-        self.print_header();
-        ncurses::attroff(ncurses::A_STANDOUT());
-        ncurses::attroff(ncurses::A_BOLD());
+        self.terminal.draw_box(top, left, height, width);
+        self.addstr_at(top, left + 2, " Help ");
+        for (i, (keys, description)) in Self::HELP.iter().enumerate() {
+            let line = format!(
+                "{:width$}{}",
+                keys,
+                description,
+                width = Self::HELP_KEY_WIDTH
+            );
+            self.addstr_at(
+                top + 1 + i as i32,
+                left + 2,
+                truncate(&line, (width - 4).max(0) as usize),
+            );
+        }
+        self.addstr_at(top + height - 2, left + 2, "Press any key to close");
     }
 
-    fn print_header(&self) {
-        self.addstr_at(0, Self::MAC_POS, "MAC");
-        self.addstr_at(0, Self::IPV4_POS, "IPv4");
-        self.addstr_at(0, Self::IPV6_POS, "IPv6");
-        self.addstr_at(0, Self::SPEED_POS, "Speed");
-        self.addstr_at(0, Self::LEASE_POS, "Lease");
-        self.addstr_at(0, Self::HOSTNAME_POS, "Host");
+    fn print_row(&self, y: i32, row: &Row) {
+        if self.theme == Theme::Mono {
+            let marker = match row {
+                Row::Added(_) => "+",
+                Row::Removed(_) => "-",
+                Row::Present { old, new } if has_changed(old, new) => "*",
+                Row::Present { .. } => " ",
+            };
+            self.addstr_at(y, 0, marker);
+        }
+        match row {
+            Row::Added(client) => self.print_new_client(y, client),
+            Row::Removed(client) => self.print_removed_client(y, client),
+            Row::Present { old, new } => self.print_client_diff(y, old, new),
+        }
     }
 
-    fn print_client(&self, i: i32, client: &ClientInfo) {
-        self.addstr_at(i, Self::MAC_POS, &client.mac);
+    // Departed devices are greyed out.
+    fn print_removed_client(&self, i: i32, client: &ClientInfo) {
+        self.terminal.attr_on(Attributes::DIM);
+        self.print_client(i, client, false);
+        self.terminal.attr_off(Attributes::DIM);
+    }
+
+    fn print_new_client(&self, i: i32, client: &ClientInfo) {
+        let attributes = self.style_attributes(Style::Added);
+        self.terminal.attr_on(attributes);
+        self.print_client(i, client, true);
+        self.terminal.attr_off(attributes);
+    }
+
+    fn print_client_diff(&self, i: i32, oldclient: &ClientInfo, newclient: &ClientInfo) {
+        for layout in &self.layout {
+            let x = layout.x;
+            let old = self.fit_cell(layout, oldclient, true);
+            let new = self.fit_cell(layout, newclient, true);
+            match layout.column {
+                Column::Ipv4 | Column::Ipv6 => {
+                    let non_empty = |s: &&String| !s.is_empty();
+                    self.addstr_at_option_diff(
+                        i,
+                        x,
+                        Some(&old).filter(non_empty),
+                        Some(&new).filter(non_empty),
+                    )
+                }
+                Column::Speed => {
+                    self.addstr_at_cmp(i, x, &new, newclient.speed.cmp(&oldclient.speed))
+                }
+                // The lease time naturally decreases between refreshes, so only renewals and
+                // leases about to expire are highlighted.
+                Column::Lease => {
+                    if newclient.lease_time > oldclient.lease_time {
+                        self.addstr_at_style(i, x, &new, Style::Added);
+                    } else if newclient.lease_time < Self::LEASE_EXPIRY_WARNING {
+                        self.addstr_at_style(i, x, &new, Style::Removed);
+                    } else {
+                        self.addstr_at(i, x, &new);
+                    }
+                }
+                _ => self.addstr_at_diff(i, x, &old, &new),
+            }
+        }
+    }
 
-        if let Some(ipv4) = &client.ipv4 {
-            self.addstr_at(i, Self::IPV4_POS, ipv4);
+    // Text of a cell, right-aligned for numbers, and shortened with an ellipsis if it doesn't fit
+    // in its column. The last column is only clipped at the edge of the screen.
+    fn fit_cell(&self, layout: &ColumnLayout, client: &ClientInfo, online: bool) -> String {
+        let cell = self.cell(layout.column, client, online);
+        let is_last = std::ptr::eq(layout, self.layout.last().unwrap());
+        if layout.column == Column::Speed {
+            format!("{:>width$}", cell, width = layout.width)
+        } else if is_last || text::width(&cell) <= layout.width {
+            cell
+        } else {
+            format!("{}…", truncate(&cell, layout.width.saturating_sub(1)))
         }
-        if let Some(ipv6) = &client.ipv6 {
-            self.addstr_at(i, Self::IPV6_POS, ipv6);
+    }
+
+    fn print_header_styled(&self) {
+        let attributes = if self.theme == Theme::Mono {
+            Attributes::BOLD | Attributes::UNDERLINE
+        } else {
+            Attributes::BOLD | Attributes::REVERSE
+        };
+        self.terminal.attr_on(attributes);
+        if self.theme != Theme::Mono {
+            self.fill_row(Self::HEADER_ROW);
         }
+        self.print_header();
+        self.terminal.attr_off(attributes);
+    }
 
-        let raw_speed = format!("{:5}", client.speed);
-        let trimmed_speed = raw_speed.trim_start();
-        let trimmed_count = (raw_speed.len() - trimmed_speed.len()) as i32;
-        self.addstr_at(i, Self::SPEED_POS + trimmed_count, &trimmed_speed);
+    // Fills a row with spaces, so that the current attributes apply to the whole width.
+    fn fill_row(&self, y: i32) {
+        self.addstr_at(y, 0, &" ".repeat(self.size.x.max(0) as usize));
+    }
+
+    fn print_header(&self) {
+        for layout in &self.layout {
+            let title = layout.column.title();
+            if layout.column == self.sort {
+                self.addstr_at(
+                    Self::HEADER_ROW,
+                    layout.x,
+                    &format!("{}{}", title, self.sort_arrow()),
+                );
+            } else {
+                self.addstr_at(Self::HEADER_ROW, layout.x, title);
+            }
+        }
+    }
 
-        self.addstr_at(i, Self::LEASE_POS, &client.lease_time);
-        self.addstr_at(i, Self::HOSTNAME_POS, &client.hostname);
+    fn sort_arrow(&self) -> &'static str {
+        if self.descending {
+            "▼"
+        } else {
+            "▲"
+        }
     }
 
-    fn addstr_at_option_diff(&self, y: i32, x: i32, old: Option<&String>, new: Option<&String>) {
+    fn print_client(&self, i: i32, client: &ClientInfo, online: bool) {
+        for layout in &self.layout {
+            self.addstr_at(i, layout.x, &self.fit_cell(layout, client, online));
+        }
+    }
+
+    fn addstr_at_option_diff<T: ToString>(&self, y: i32, x: i32, old: Option<&T>, new: Option<&T>) {
         match (old, new) {
-            (Some(o), Some(n)) => self.addstr_at_diff(y, x, o, n),
+            (Some(o), Some(n)) => self.addstr_at_diff(y, x, &o.to_string(), &n.to_string()),
             (Some(o), None) => {
-                self.addstr_at_pair(y, x, o, Self::REMOVED_PAIR);
+                self.addstr_at_style(y, x, &o.to_string(), Style::Removed);
             }
             (None, Some(n)) => {
-                self.addstr_at_pair(y, x, n, Self::ADDED_PAIR);
+                self.addstr_at_style(y, x, &n.to_string(), Style::Added);
             }
             (None, None) => (),
         }
@@ -239,29 +1629,48 @@ impl Tui {
                 self.addstr_at(y, x, s);
             }
             Ordering::Less => {
-                self.addstr_at_pair(y, x, s, Self::REMOVED_PAIR);
+                self.addstr_at_style(y, x, s, Style::Removed);
             }
             Ordering::Greater => {
-                self.addstr_at_pair(y, x, s, Self::ADDED_PAIR);
+                self.addstr_at_style(y, x, s, Style::Added);
             }
         }
     }
 
     fn addstr_at_diff(&self, y: i32, x: i32, old: &str, new: &str) {
         if new != old {
-            self.addstr_at_pair(y, x, new, Self::HIGHLIGHT_PAIR);
+            self.addstr_at_style(y, x, new, Style::Changed);
         } else {
             self.addstr_at(y, x, new);
         }
     }
 
-    fn addstr_at_pair(&self, y: i32, x: i32, s: &str, pair: i16) {
-        ncurses::attron(ncurses::COLOR_PAIR(pair));
+    fn addstr_at_quality(&self, y: i32, x: i32, s: &str, quality: Quality) {
+        let style = match quality {
+            Quality::Good => Style::Good,
+            Quality::Marginal => Style::Marginal,
+            Quality::Bad => Style::Bad,
+        };
+        self.addstr_at_style(y, x, s, style);
+    }
+
+    fn addstr_at_style(&self, y: i32, x: i32, s: &str, style: Style) {
+        let attributes = self.style_attributes(style);
+        self.terminal.attr_on(attributes);
         self.addstr_at(y, x, s);
-        ncurses::attroff(ncurses::COLOR_PAIR(pair));
+        self.terminal.attr_off(attributes);
+    }
+
+    // Color pair of a style, or text attributes in the monochrome theme.
+    fn style_attributes(&self, style: Style) -> Attributes {
+        if self.theme == Theme::Mono {
+            style.mono_attributes()
+        } else {
+            Attributes::style(style)
+        }
     }
 
-    fn addstr_at(&self, y: i32, x: i32, mut s: &str) {
+    fn addstr_at(&self, y: i32, x: i32, s: &str) {
         if y >= self.size.y {
             return;
         }
@@ -269,18 +1678,149 @@ impl Tui {
             return;
         }
 
-        let available_len = (self.size.x - x) as usize;
-        if s.len() >= available_len {
-            s = &s[..available_len];
-        }
+        let available_width = (self.size.x - x) as usize;
+        self.terminal
+            .print(y, x, text::truncate(s, available_width));
+    }
+}
+
+// Formats the local time at which a lease expires.
+fn format_lease_expiry(lease_time: Duration) -> String {
+    let expiry = Local::now()
+        + chrono::Duration::from_std(lease_time).unwrap_or_else(|_| chrono::Duration::zero());
+    format_time(&expiry)
+}
+
+// Whether a device changed since the previous refresh, apart from its lease time counting down.
+fn has_changed(old: &ClientInfo, new: &ClientInfo) -> bool {
+    let renewed = new.lease_time > old.lease_time;
+    let old = ClientInfo {
+        lease_time: new.lease_time,
+        ..old.clone()
+    };
+    renewed || old != *new
+}
+
+// Formats the time elapsed since a past time, to the minute after the first minute.
+fn format_elapsed(since: &DateTime<Local>) -> String {
+    let elapsed = Local::now() - *since;
+    if elapsed < chrono::Duration::minutes(1) {
+        history::format_duration(elapsed)
+    } else {
+        history::format_duration(chrono::Duration::minutes(elapsed.num_minutes()))
+    }
+}
+
+// Formats a time, with the day of the week if it isn't today.
+fn format_time(time: &DateTime<Local>) -> String {
+    if time.date_naive() == Local::now().date_naive() {
+        time.format("%H:%M").to_string()
+    } else {
+        time.format("%a %H:%M").to_string()
+    }
+}
 
-        ncurses::wmove(self.window, y, x);
-        ncurses::addstr(s);
+// Adds a section title to label/value lines, separated from the previous section.
+fn push_section(lines: &mut Vec<(&str, String)>, title: &'static str) {
+    if !lines.is_empty() {
+        lines.push(("", String::new()));
     }
+    lines.push((title, String::new()));
 }
 
-impl Drop for Tui {
-    fn drop(&mut self) {
-        ncurses::endwin();
+fn format_wan_addresses(state: &CmState) -> String {
+    let mut addresses: Vec<String> = state
+        .wan_ipv4_addr
+        .iter()
+        .map(|ip| ip.to_string())
+        .collect();
+    addresses.extend(state.wan_ipv6_addr.addresses.iter().cloned());
+    if addresses.is_empty() {
+        "-".to_owned()
+    } else {
+        addresses.join(" ")
     }
 }
+
+// Formats an uptime, to the minute.
+fn format_uptime(uptime: Duration) -> String {
+    let minutes = uptime.as_secs() / 60;
+    history::format_duration(chrono::Duration::minutes(minutes as i64))
+}
+
+// Summarizes how many downstream channels are fully locked.
+fn lock_summary(channels: &DocsisChannels) -> String {
+    let locked = channels
+        .downstream
+        .iter()
+        .filter(|channel| channel.is_locked())
+        .count();
+    format!(
+        "downstream {}/{} locked, upstream {} channels",
+        locked,
+        channels.downstream.len(),
+        channels.upstream.len()
+    )
+}
+
+// Applies a key press to a text being typed.
+fn edit_text(text: &mut String, key: Key) -> TextInput {
+    match key {
+        Key::Escape => TextInput::Cancelled,
+        Key::Enter => TextInput::Done,
+        Key::Backspace => {
+            text.pop();
+            TextInput::Edited
+        }
+        Key::Char(c) => {
+            text.push(c);
+            TextInput::Edited
+        }
+        _ => TextInput::Ignored,
+    }
+}
+
+// Copies text to the clipboard of the terminal emulator, with an OSC 52 escape sequence.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64::encode(text))?;
+    stdout.flush()
+}
+
+fn format_frequency(frequency: u64) -> String {
+    format!("{:.1} MHz", frequency as f64 / 1e6)
+}
+
+// Draws the most recent samples of a channel measurement.
+fn recent_sparkline(samples: &VecDeque<f64>, min: f64, max: f64) -> String {
+    let skip = samples.len().saturating_sub(Tui::SPARKLINE_WIDTH);
+    let values: Vec<f64> = samples.iter().skip(skip).cloned().collect();
+    sparkline(&values, min, max)
+}
+
+// Draws values as a line of block characters, scaled between `min` and `max`.
+fn sparkline(values: &[f64], min: f64, max: f64) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    values
+        .iter()
+        .map(|&value| {
+            let ratio = if max > min {
+                ((value - min) / (max - min)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            BLOCKS[(ratio * (BLOCKS.len() - 1) as f64).round() as usize]
+        })
+        .collect()
+}
+
+// Whether a client matches a (lowercase) filter, by hostname, MAC address, IP address or alias.
+fn matches_filter(client: &ClientInfo, aliases: &Aliases, filter: &str) -> bool {
+    let matches = |s: &str| s.to_lowercase().contains(filter);
+    filter.is_empty()
+        || matches(&client.hostname)
+        || matches(&client.mac.to_string())
+        || client.ipv4.map_or(false, |ipv4| matches(&ipv4.to_string()))
+        || client.ipv6.map_or(false, |ipv6| matches(&ipv6.to_string()))
+        || aliases.get(&client.mac).map_or(false, matches)
+}
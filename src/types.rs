@@ -1,6 +1,10 @@
-use serde::{Deserialize, Serialize};
+use crate::correlation;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::Ordering;
 use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LanUserTable {
@@ -20,21 +24,277 @@ pub struct Wifi {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ClientInfo {
     #[serde(rename = "MACAddr")]
-    pub mac: String,
+    pub mac: MacAddr,
     pub hostname: String,
     pub index: usize,
-    #[serde(rename = "IPv4Addr")]
-    pub ipv4: Option<String>,
-    #[serde(rename = "IPv6Addr")]
-    pub ipv6: Option<String>,
+    #[serde(rename = "IPv4Addr", default, with = "cidr")]
+    pub ipv4: Option<Ipv4Addr>,
+    #[serde(rename = "IPv6Addr", default, with = "cidr")]
+    pub ipv6: Option<Ipv6Addr>,
+    /// Name of the interface through which the client is connected, as shown by the router.
     pub interface: String,
+    /// Identifier of the interface. The firmware doesn't document which identifier is which
+    /// interface, so it is kept as is; see `interface_kind` instead.
     pub interfaceid: usize,
-    pub method: usize,
-    #[serde(rename = "leaseTime")]
-    pub lease_time: String,
+    pub method: AddressMethod,
+    #[serde(rename = "leaseTime", with = "lease_time")]
+    pub lease_time: Duration,
     pub speed: usize,
 }
 
+impl ClientInfo {
+    /// Kind of interface through which the client is connected.
+    pub fn interface_kind(&self) -> InterfaceKind {
+        InterfaceKind::from_name(&self.interface, self.interfaceid)
+    }
+}
+
+/// A MAC address.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MacAddr(pub [u8; 6]);
+
+impl MacAddr {
+    /// Whether the address is locally administered, i.e. the second-least-significant bit of
+    /// its first octet is set. This is the case for randomized addresses.
+    pub fn is_locally_administered(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let b = &self.0;
+        write!(
+            f,
+            "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
+            b[0], b[1], b[2], b[3], b[4], b[5]
+        )
+    }
+}
+
+impl fmt::Debug for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseMacAddrError;
+
+impl fmt::Display for ParseMacAddrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid MAC address")
+    }
+}
+
+impl std::error::Error for ParseMacAddrError {}
+
+impl FromStr for MacAddr {
+    type Err = ParseMacAddrError;
+
+    /// Parses a MAC address as 6 hexadecimal octets, separated by colons or dashes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0; 6];
+        let mut parts = s.split([':', '-']);
+        for byte in bytes.iter_mut() {
+            let part = parts.next().ok_or(ParseMacAddrError)?;
+            if part.len() != 2 {
+                return Err(ParseMacAddrError);
+            }
+            *byte = u8::from_str_radix(part, 16).map_err(|_| ParseMacAddrError)?;
+        }
+        if parts.next().is_some() {
+            return Err(ParseMacAddrError);
+        }
+        Ok(MacAddr(bytes))
+    }
+}
+
+impl Serialize for MacAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MacAddr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// How a client obtained its IP address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AddressMethod {
+    Dhcp,
+    Static,
+    /// Unrecognized method identifier.
+    Other(usize),
+}
+
+impl AddressMethod {
+    fn from_id(id: usize) -> Self {
+        match id {
+            1 => AddressMethod::Dhcp,
+            2 => AddressMethod::Static,
+            id => AddressMethod::Other(id),
+        }
+    }
+
+    fn id(self) -> usize {
+        match self {
+            AddressMethod::Dhcp => 1,
+            AddressMethod::Static => 2,
+            AddressMethod::Other(id) => id,
+        }
+    }
+}
+
+impl fmt::Display for AddressMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressMethod::Dhcp => f.write_str("DHCP"),
+            AddressMethod::Static => f.write_str("Static"),
+            AddressMethod::Other(id) => write!(f, "#{}", id),
+        }
+    }
+}
+
+impl Serialize for AddressMethod {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.id() as u64)
+    }
+}
+
+impl<'de> Deserialize<'de> for AddressMethod {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        usize::deserialize(deserializer).map(AddressMethod::from_id)
+    }
+}
+
+/// Kind of interface through which a client is connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InterfaceKind {
+    Ethernet,
+    Wifi2_4GHz,
+    Wifi5GHz,
+    /// Unrecognized interface, with its identifier.
+    Other(usize),
+}
+
+impl InterfaceKind {
+    // The kind is told by the name of the interface shown by the router, e.g. "Ethernet 1",
+    // "Wi-Fi 2.4G" or "Wi-Fi 5G".
+    fn from_name(name: &str, id: usize) -> Self {
+        let name = name.to_lowercase();
+        if name.starts_with("ethernet") {
+            InterfaceKind::Ethernet
+        } else if name.starts_with("wi-fi") || name.starts_with("wifi") {
+            if name.contains("2.4g") {
+                InterfaceKind::Wifi2_4GHz
+            } else if name.contains("5g") {
+                InterfaceKind::Wifi5GHz
+            } else {
+                InterfaceKind::Other(id)
+            }
+        } else {
+            InterfaceKind::Other(id)
+        }
+    }
+}
+
+impl fmt::Display for InterfaceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterfaceKind::Ethernet => f.write_str("Ethernet"),
+            InterfaceKind::Wifi2_4GHz => f.write_str("2.4 GHz"),
+            InterfaceKind::Wifi5GHz => f.write_str("5 GHz"),
+            InterfaceKind::Other(id) => write!(f, "#{}", id),
+        }
+    }
+}
+
+/// Formats a lease time in the "DD:HH:MM:SS" format used by the router.
+pub fn format_lease_time(lease_time: Duration) -> String {
+    let seconds = lease_time.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        seconds / 86400,
+        seconds / 3600 % 24,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Parses a lease time in the "DD:HH:MM:SS" format used by the router.
+pub fn parse_lease_time(s: &str) -> Option<Duration> {
+    let mut parts = s.split(':');
+    let mut seconds = 0;
+    for unit in &[86400, 3600, 60, 1] {
+        seconds += parts.next()?.parse::<u64>().ok()? * unit;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(Duration::from_secs(seconds))
+}
+
+// (De)serialization of lease times.
+mod lease_time {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        lease_time: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::format_lease_time(*lease_time))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_lease_time(&s)
+            .ok_or_else(|| de::Error::custom(format!("invalid lease time: {:?}", s)))
+    }
+}
+
+// (De)serialization of optional IP addresses, which the router may suffix with the prefix length
+// (e.g. "192.168.0.10/24"). The prefix length is that of the LAN rather than a property of the
+// client, the same for all of them, so it is discarded. Empty strings are treated as missing
+// addresses.
+mod cidr {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T: Display, S: Serializer>(
+        addr: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match addr {
+            Some(addr) => serializer.collect_str(addr),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let s = match Option::<String>::deserialize(deserializer)? {
+            Some(s) => s,
+            None => return Ok(None),
+        };
+        let addr = s.split('/').next().unwrap().trim();
+        if addr.is_empty() {
+            return Ok(None);
+        }
+        addr.parse().map(Some).map_err(de::Error::custom)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CmState {
     #[serde(rename = "TunnerTemperature")]
@@ -43,6 +303,8 @@ pub struct CmState {
     pub temperature: usize,
     #[serde(rename = "OperState")]
     pub oper_state: String,
+    #[serde(default, with = "cidr")]
+    pub wan_ipv4_addr: Option<Ipv4Addr>,
     pub wan_ipv6_addr: WanIpv6Addr,
 }
 
@@ -52,9 +314,160 @@ pub struct WanIpv6Addr {
     pub addresses: Vec<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GlobalSettings {
+    #[serde(rename = "ConfigVenderModel", default)]
+    pub model: String,
+    #[serde(rename = "SwVersion", default)]
+    pub sw_version: String,
+    #[serde(rename = "CmProvisionMode", default)]
+    pub cm_provision_mode: String,
+    #[serde(rename = "GwProvisionMode", default)]
+    pub gw_provision_mode: String,
+    #[serde(rename = "OperatorId", default)]
+    pub operator_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CmSystemInfo {
+    #[serde(rename = "cm_docsis_mode")]
+    pub docsis_mode: String,
+    #[serde(rename = "cm_hardware_version")]
+    pub hardware_version: String,
+    #[serde(rename = "cm_mac_addr")]
+    pub mac: String,
+    #[serde(rename = "cm_serial_number")]
+    pub serial_number: String,
+    #[serde(rename = "cm_system_uptime", with = "uptime")]
+    pub uptime: Duration,
+    #[serde(rename = "cm_network_access")]
+    pub network_access: String,
+}
+
+/// Parses an uptime in the "37day(s)14h:37m:50s" format of the router.
+pub fn parse_uptime(s: &str) -> Option<Duration> {
+    let (days, rest) = s.split_once("day(s)")?;
+    let mut seconds = days.trim().parse::<u64>().ok()? * 86400;
+    let mut parts = rest.split(':');
+    for (unit, suffix) in &[(3600, 'h'), (60, 'm'), (1, 's')] {
+        let part = parts.next()?.trim().strip_suffix(*suffix)?;
+        seconds += part.parse::<u64>().ok()? * unit;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(Duration::from_secs(seconds))
+}
+
+pub fn format_uptime(uptime: Duration) -> String {
+    let seconds = uptime.as_secs();
+    format!(
+        "{}day(s){}h:{}m:{}s",
+        seconds / 86400,
+        (seconds / 3600) % 24,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
+
+// (De)serialization of uptimes.
+mod uptime {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(uptime: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::format_uptime(*uptime))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_uptime(&s).ok_or_else(|| de::Error::custom(format!("invalid uptime: {:?}", s)))
+    }
+}
+
+/// Downstream and upstream channels of the cable modem.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocsisChannels {
+    pub downstream: Vec<DownstreamChannel>,
+    pub upstream: Vec<UpstreamChannel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownstreamTable {
+    #[serde(rename = "downstream", default)]
+    pub channels: Vec<DownstreamChannel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownstreamChannel {
+    #[serde(rename = "chid")]
+    pub id: usize,
+    /// Frequency, in Hz.
+    #[serde(rename = "freq")]
+    pub frequency: u64,
+    /// Power level, in dBmV.
+    #[serde(rename = "pow")]
+    pub power: f64,
+    /// Signal to noise ratio, in dB.
+    #[serde(rename = "RxMER")]
+    pub snr: f64,
+    #[serde(rename = "mod")]
+    pub modulation: String,
+    /// Number of corrected codeword errors.
+    #[serde(rename = "PreRs")]
+    pub corrected: u64,
+    /// Number of uncorrectable codeword errors.
+    #[serde(rename = "PostRs")]
+    pub uncorrectable: u64,
+    #[serde(rename = "IsQamLocked")]
+    pub qam_locked: bool,
+    #[serde(rename = "IsFECLocked")]
+    pub fec_locked: bool,
+    #[serde(rename = "IsMpegLocked")]
+    pub mpeg_locked: bool,
+}
+
+impl DownstreamChannel {
+    pub fn is_locked(&self) -> bool {
+        self.qam_locked && self.fec_locked && self.mpeg_locked
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpstreamTable {
+    #[serde(rename = "upstream", default)]
+    pub channels: Vec<UpstreamChannel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpstreamChannel {
+    #[serde(rename = "usid")]
+    pub id: usize,
+    /// Frequency, in Hz.
+    #[serde(rename = "freq")]
+    pub frequency: u64,
+    /// Power level, in dBmV.
+    pub power: f64,
+    /// Symbol rate, in ksym/s.
+    #[serde(rename = "srate")]
+    pub symbol_rate: f64,
+    #[serde(rename = "mod")]
+    pub modulation: String,
+    #[serde(rename = "t1Timeouts")]
+    pub t1_timeouts: u64,
+    #[serde(rename = "t2Timeouts")]
+    pub t2_timeouts: u64,
+    #[serde(rename = "t3Timeouts")]
+    pub t3_timeouts: u64,
+    #[serde(rename = "t4Timeouts")]
+    pub t4_timeouts: u64,
+}
+
 pub struct LanUserTableDiff<'a> {
     pub old: &'a LanUserTable,
     pub new: &'a LanUserTable,
+    /// Departed devices to match randomized MAC addresses against, if MAC correlation is enabled.
+    pub departed: Option<&'a [ClientInfo]>,
 }
 
 impl fmt::Debug for LanUserTableDiff<'_> {
@@ -82,6 +495,7 @@ impl fmt::Debug for LanUserTableDiff<'_> {
                 &WifiDiff {
                     old: &self.old.wifi,
                     new: &self.new.wifi,
+                    departed: self.departed,
                 },
             );
         }
@@ -92,6 +506,7 @@ impl fmt::Debug for LanUserTableDiff<'_> {
 struct WifiDiff<'a> {
     old: &'a Wifi,
     new: &'a Wifi,
+    departed: Option<&'a [ClientInfo]>,
 }
 
 impl fmt::Debug for WifiDiff<'_> {
@@ -106,15 +521,76 @@ impl fmt::Debug for WifiDiff<'_> {
                 &ClientInfosDiff {
                     old: &self.old.clientinfo,
                     new: &self.new.clientinfo,
+                    departed: self.departed,
                 },
             )
             .finish()
     }
 }
 
+/// A change to a single client between two snapshots of the client list.
+pub enum ClientChange<'a> {
+    Added(&'a ClientInfo),
+    Removed(&'a ClientInfo),
+    Updated {
+        old: &'a ClientInfo,
+        new: &'a ClientInfo,
+    },
+    /// A device that left and came back under a different (randomized) MAC address.
+    Reconnected {
+        old: &'a ClientInfo,
+        new: &'a ClientInfo,
+    },
+}
+
+/// Computes the list of changes between two client lists, by matching clients on their MAC
+/// address. Changes are sorted by MAC address, and unchanged clients are omitted.
+pub fn diff_clients<'a>(old: &'a [ClientInfo], new: &'a [ClientInfo]) -> Vec<ClientChange<'a>> {
+    let mut changes = Vec::new();
+    let mut sorted_old = old.iter().collect::<Vec<_>>();
+    sorted_old.sort_by_key(|x| &x.mac);
+    let mut sorted_new = new.iter().collect::<Vec<_>>();
+    sorted_new.sort_by_key(|x| &x.mac);
+
+    let mut old_it = sorted_old.into_iter().peekable();
+    let mut new_it = sorted_new.into_iter().peekable();
+    loop {
+        match (old_it.peek(), new_it.peek()) {
+            (Some(o), Some(n)) => match o.mac.cmp(&n.mac) {
+                Ordering::Equal => {
+                    if o != n {
+                        changes.push(ClientChange::Updated { old: o, new: n });
+                    }
+                    new_it.next();
+                    old_it.next();
+                }
+                Ordering::Less => {
+                    changes.push(ClientChange::Removed(o));
+                    old_it.next();
+                }
+                Ordering::Greater => {
+                    changes.push(ClientChange::Added(n));
+                    new_it.next();
+                }
+            },
+            (None, Some(n)) => {
+                changes.push(ClientChange::Added(n));
+                new_it.next();
+            }
+            (Some(o), None) => {
+                changes.push(ClientChange::Removed(o));
+                old_it.next();
+            }
+            (None, None) => break,
+        }
+    }
+    changes
+}
+
 struct ClientInfosDiff<'a> {
     old: &'a [ClientInfo],
     new: &'a [ClientInfo],
+    departed: Option<&'a [ClientInfo]>,
 }
 
 impl fmt::Debug for ClientInfosDiff<'_> {
@@ -123,43 +599,22 @@ impl fmt::Debug for ClientInfosDiff<'_> {
             return Ok(());
         }
 
+        let mut changes = diff_clients(self.old, self.new);
+        if let Some(departed) = self.departed {
+            changes = correlation::correlate(changes, departed);
+        }
+
         let mut dbg = f.debug_list();
-        let mut sorted_old = self.old.iter().collect::<Vec<_>>();
-        sorted_old.sort_by_key(|x| &x.mac);
-        let mut sorted_new = self.new.iter().collect::<Vec<_>>();
-        sorted_new.sort_by_key(|x| &x.mac);
-
-        let mut old_it = sorted_old.iter().peekable();
-        let mut new_it = sorted_new.iter().peekable();
-        loop {
-            match (old_it.peek(), new_it.peek()) {
-                (Some(o), Some(n)) => match o.mac.cmp(&n.mac) {
-                    Ordering::Equal => {
-                        if o != n {
-                            dbg.entry(&ClientInfoDiff { old: o, new: n });
-                        }
-                        new_it.next();
-                        old_it.next();
-                    }
-                    Ordering::Less => {
-                        dbg.entry(&format_args!("{:#?} => ()", o));
-                        old_it.next();
-                    }
-                    Ordering::Greater => {
-                        dbg.entry(&format_args!("() => {:#?}", n));
-                        new_it.next();
-                    }
-                },
-                (None, Some(n)) => {
-                    dbg.entry(&format_args!("() => {:#?}", n));
-                    new_it.next();
-                }
-                (Some(o), None) => {
-                    dbg.entry(&format_args!("{:#?} => ()", o));
-                    old_it.next();
-                }
-                (None, None) => break,
-            }
+        for change in changes {
+            match change {
+                ClientChange::Added(n) => dbg.entry(&format_args!("() => {:#?}", n)),
+                ClientChange::Removed(o) => dbg.entry(&format_args!("{:#?} => ()", o)),
+                ClientChange::Updated { old, new } => dbg.entry(&ClientInfoDiff { old, new }),
+                ClientChange::Reconnected { old, new } => dbg.entry(&format_args!(
+                    "Reconnected {:#?}",
+                    ClientInfoDiff { old, new }
+                )),
+            };
         }
         dbg.finish()
     }
@@ -246,3 +701,52 @@ impl fmt::Debug for ClientInfoDiff<'_> {
         dbg.finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Addresses {
+        #[serde(default, with = "cidr")]
+        ipv4: Option<Ipv4Addr>,
+        #[serde(default, with = "cidr")]
+        ipv6: Option<Ipv6Addr>,
+    }
+
+    #[test]
+    fn addresses_with_prefix_length() {
+        let addresses: Addresses =
+            serde_json::from_str(r#"{"ipv4": "192.168.0.10/24", "ipv6": "2001:db8::1/64"}"#)
+                .unwrap();
+        assert_eq!(addresses.ipv4, Some(Ipv4Addr::new(192, 168, 0, 10)));
+        assert_eq!(addresses.ipv6, Some("2001:db8::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn missing_addresses() {
+        let addresses: Addresses = serde_json::from_str(r#"{"ipv4": ""}"#).unwrap();
+        assert_eq!(addresses.ipv4, None);
+        assert_eq!(addresses.ipv6, None);
+    }
+
+    #[test]
+    fn interface_kinds() {
+        let kind = |name| InterfaceKind::from_name(name, 7);
+        assert_eq!(kind("Ethernet 1"), InterfaceKind::Ethernet);
+        assert_eq!(kind("Wi-Fi 2.4G"), InterfaceKind::Wifi2_4GHz);
+        assert_eq!(kind("Wi-Fi 5G"), InterfaceKind::Wifi5GHz);
+        assert_eq!(kind("WiFi 5GHz"), InterfaceKind::Wifi5GHz);
+        assert_eq!(kind("Wi-Fi"), InterfaceKind::Other(7));
+        assert_eq!(kind("MoCA"), InterfaceKind::Other(7));
+    }
+
+    #[test]
+    fn lease_time() {
+        assert_eq!(
+            parse_lease_time("01:02:03:04"),
+            Some(Duration::from_secs(((24 + 2) * 60 + 3) * 60 + 4))
+        );
+        assert_eq!(parse_lease_time("02:03:04"), None);
+    }
+}
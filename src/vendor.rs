@@ -0,0 +1,114 @@
+use crate::types::MacAddr;
+
+// Organizationally unique identifiers (first three octets of a MAC address) of common consumer
+// device manufacturers. This is by no means exhaustive, the full registry is maintained by the
+// IEEE at https://standards-oui.ieee.org/.
+const OUIS: &[([u8; 3], &str)] = &[
+    ([0x00, 0x03, 0x93], "Apple"),
+    ([0x00, 0x0A, 0x95], "Apple"),
+    ([0x00, 0x1B, 0x63], "Apple"),
+    ([0x00, 0x1E, 0xC2], "Apple"),
+    ([0x00, 0x25, 0x00], "Apple"),
+    ([0x28, 0xCF, 0xE9], "Apple"),
+    ([0x3C, 0x07, 0x54], "Apple"),
+    ([0x40, 0x6C, 0x8F], "Apple"),
+    ([0x68, 0xA8, 0x6D], "Apple"),
+    ([0x70, 0x56, 0x81], "Apple"),
+    ([0x7C, 0xD1, 0xC3], "Apple"),
+    ([0xA4, 0x5E, 0x60], "Apple"),
+    ([0xAC, 0xBC, 0x32], "Apple"),
+    ([0xD0, 0x23, 0xDB], "Apple"),
+    ([0xF0, 0x18, 0x98], "Apple"),
+    ([0xF4, 0x0F, 0x24], "Apple"),
+    ([0x00, 0x0C, 0x6E], "ASUSTek"),
+    ([0x00, 0x1A, 0x92], "ASUSTek"),
+    ([0x04, 0xD4, 0xC4], "ASUSTek"),
+    ([0x2C, 0x56, 0xDC], "ASUSTek"),
+    ([0x00, 0x04, 0x0E], "AVM"),
+    ([0x3C, 0xA6, 0x2F], "AVM"),
+    ([0xC8, 0x0E, 0x14], "AVM"),
+    ([0x00, 0x14, 0x22], "Dell"),
+    ([0x18, 0x03, 0x73], "Dell"),
+    ([0xB8, 0xAC, 0x6F], "Dell"),
+    ([0xF0, 0x4D, 0xA2], "Dell"),
+    ([0x24, 0x0A, 0xC4], "Espressif"),
+    ([0x24, 0x6F, 0x28], "Espressif"),
+    ([0x30, 0xAE, 0xA4], "Espressif"),
+    ([0x5C, 0xCF, 0x7F], "Espressif"),
+    ([0x84, 0xF3, 0xEB], "Espressif"),
+    ([0xA4, 0xCF, 0x12], "Espressif"),
+    ([0xEC, 0xFA, 0xBC], "Espressif"),
+    ([0x3C, 0x5A, 0xB4], "Google"),
+    ([0x54, 0x60, 0x09], "Google"),
+    ([0xF4, 0xF5, 0xD8], "Google"),
+    ([0xF8, 0x8F, 0xCA], "Google"),
+    ([0x00, 0x1E, 0x0B], "HP"),
+    ([0x3C, 0xD9, 0x2B], "HP"),
+    ([0x9C, 0x8E, 0x99], "HP"),
+    ([0x00, 0x02, 0xB3], "Intel"),
+    ([0x00, 0x03, 0x47], "Intel"),
+    ([0x00, 0x0E, 0x0C], "Intel"),
+    ([0x00, 0x13, 0xE8], "Intel"),
+    ([0x00, 0x1B, 0x21], "Intel"),
+    ([0x00, 0x1C, 0xC0], "Intel"),
+    ([0xA0, 0x36, 0x9F], "Intel"),
+    ([0x00, 0x15, 0x5D], "Microsoft"),
+    ([0x00, 0x50, 0xF2], "Microsoft"),
+    ([0x28, 0x18, 0x78], "Microsoft"),
+    ([0x7C, 0x1E, 0x52], "Microsoft"),
+    ([0x18, 0xB4, 0x30], "Nest Labs"),
+    ([0x64, 0x16, 0x66], "Nest Labs"),
+    ([0x00, 0x09, 0x5B], "Netgear"),
+    ([0x00, 0x14, 0x6C], "Netgear"),
+    ([0x20, 0x4E, 0x7F], "Netgear"),
+    ([0xA0, 0x40, 0xA0], "Netgear"),
+    ([0x00, 0x09, 0xBF], "Nintendo"),
+    ([0x00, 0x1F, 0x32], "Nintendo"),
+    ([0x98, 0xB6, 0xE9], "Nintendo"),
+    ([0x00, 0x17, 0x88], "Philips Hue"),
+    ([0xEC, 0xB5, 0xFA], "Philips Hue"),
+    ([0xB8, 0x27, 0xEB], "Raspberry Pi"),
+    ([0xD8, 0x3A, 0xDD], "Raspberry Pi"),
+    ([0xDC, 0xA6, 0x32], "Raspberry Pi"),
+    ([0xE4, 0x5F, 0x01], "Raspberry Pi"),
+    ([0x00, 0xE0, 0x4C], "Realtek"),
+    ([0xB0, 0xA7, 0x37], "Roku"),
+    ([0xD8, 0x31, 0x34], "Roku"),
+    ([0x00, 0x12, 0xFB], "Samsung"),
+    ([0x00, 0x15, 0x99], "Samsung"),
+    ([0x00, 0x16, 0x32], "Samsung"),
+    ([0x00, 0x1D, 0x25], "Samsung"),
+    ([0x00, 0x0E, 0x58], "Sonos"),
+    ([0x5C, 0xAA, 0xFD], "Sonos"),
+    ([0x94, 0x9F, 0x3E], "Sonos"),
+    ([0xB8, 0xE9, 0x37], "Sonos"),
+    ([0x00, 0x04, 0x1F], "Sony"),
+    ([0x00, 0x13, 0x15], "Sony"),
+    ([0x00, 0x19, 0xC5], "Sony"),
+    ([0x00, 0x1D, 0x0D], "Sony"),
+    ([0x14, 0xCC, 0x20], "TP-Link"),
+    ([0x50, 0xC7, 0xBF], "TP-Link"),
+    ([0x98, 0xDE, 0xD0], "TP-Link"),
+    ([0xC0, 0x4A, 0x00], "TP-Link"),
+    ([0x04, 0x18, 0xD6], "Ubiquiti"),
+    ([0x24, 0xA4, 0x3C], "Ubiquiti"),
+    ([0x78, 0x8A, 0x20], "Ubiquiti"),
+    ([0x80, 0x2A, 0xA8], "Ubiquiti"),
+    ([0xF0, 0x9F, 0xC2], "Ubiquiti"),
+    ([0xFC, 0xEC, 0xDA], "Ubiquiti"),
+    ([0x00, 0x05, 0x69], "VMware"),
+    ([0x00, 0x0C, 0x29], "VMware"),
+    ([0x00, 0x50, 0x56], "VMware"),
+];
+
+/// Name of the manufacturer of a device, based on the OUI of its MAC address. Randomized (locally
+/// administered) addresses don't carry this information.
+pub fn lookup(mac: &MacAddr) -> Option<&'static str> {
+    if mac.is_locally_administered() {
+        return None;
+    }
+    let oui = [mac.0[0], mac.0[1], mac.0[2]];
+    OUIS.iter()
+        .find(|(prefix, _)| *prefix == oui)
+        .map(|&(_, vendor)| vendor)
+}
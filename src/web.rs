@@ -0,0 +1,592 @@
+//! Web dashboard, served over HTTP with the devices, router status and temperatures streamed to
+//! the browser as Server-Sent Events, alongside the REST API.
+
+use crate::api::{self, Call};
+use crate::correlation;
+use crate::session::SharedRouter;
+use crate::types::{
+    diff_clients, ClientChange, ClientInfo, CmState, CmSystemInfo, DocsisChannels, GlobalSettings,
+    LanUserTable, MacAddr,
+};
+use chrono::{DateTime, Utc};
+use futures::channel::mpsc;
+use futures::stream::StreamExt;
+use hyper::header::{self, HeaderValue};
+use hyper::http::uri::Authority;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::{debug, info};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+// The page of the dashboard, with its script and styles.
+const DASHBOARD_HTML: &str = include_str!("web/dashboard.html");
+
+// Number of temperature readings kept for the chart.
+const MAX_TEMPERATURE_SAMPLES: usize = 24 * 60;
+
+/// Where to serve the dashboard, and who may see it.
+pub struct Config {
+    pub listen: SocketAddr,
+    /// Credentials required with HTTP basic authentication, as "user:password".
+    pub basic_auth: Option<String>,
+}
+
+/// Latest data of the monitoring loop, shared with the HTTP connections.
+pub struct Dashboard {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    devices: Option<LanUserTable>,
+    status: Option<Status>,
+    temperatures: VecDeque<TemperatureSample>,
+    // Streams of the connected browsers.
+    subscribers: Vec<mpsc::UnboundedSender<String>>,
+}
+
+#[derive(Clone, Serialize)]
+struct Status {
+    settings: GlobalSettings,
+    system: CmSystemInfo,
+    channels: DocsisChannels,
+}
+
+#[derive(Clone, Serialize)]
+struct TemperatureSample {
+    time: DateTime<Utc>,
+    temperature: usize,
+    tuner_temperature: usize,
+}
+
+// Sent when a browser connects, before the events.
+#[derive(Serialize)]
+struct Snapshot<'a> {
+    devices: Option<&'a [ClientInfo]>,
+    status: Option<&'a Status>,
+    temperatures: &'a VecDeque<TemperatureSample>,
+}
+
+// A change in the device table, as computed for the TUI and the alerts.
+#[derive(Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+enum DeviceChange<'a> {
+    Added {
+        device: &'a ClientInfo,
+    },
+    Removed {
+        device: &'a ClientInfo,
+    },
+    Updated {
+        device: &'a ClientInfo,
+    },
+    Reconnected {
+        old: &'a ClientInfo,
+        device: &'a ClientInfo,
+    },
+}
+
+#[derive(Serialize)]
+struct DevicesEvent<'a> {
+    devices: &'a [ClientInfo],
+    changes: Vec<DeviceChange<'a>>,
+}
+
+#[derive(Serialize)]
+struct TemperatureEvent<'a> {
+    sample: &'a TemperatureSample,
+    state: &'a CmState,
+}
+
+impl Dashboard {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(State::default()),
+        })
+    }
+
+    /// Publishes the devices, and their changes since the previous refresh. Devices that reconnect
+    /// with a randomized MAC address are recognized among the departed ones, if given.
+    pub fn update_devices(&self, devices: LanUserTable, departed: Option<&[ClientInfo]>) {
+        let mut state = self.state.lock().unwrap();
+        let event = {
+            // The first devices aren't changes.
+            let old = state
+                .devices
+                .as_ref()
+                .map_or(&devices.wifi.clientinfo[..], |old| &old.wifi.clientinfo[..]);
+            let mut changes = diff_clients(old, &devices.wifi.clientinfo);
+            if let Some(departed) = departed {
+                changes = correlation::correlate(changes, departed);
+            }
+            let changes = changes
+                .into_iter()
+                .map(|change| match change {
+                    ClientChange::Added(device) => DeviceChange::Added { device },
+                    ClientChange::Removed(device) => DeviceChange::Removed { device },
+                    ClientChange::Updated { new, .. } => DeviceChange::Updated { device: new },
+                    ClientChange::Reconnected { old, new } => {
+                        DeviceChange::Reconnected { old, device: new }
+                    }
+                })
+                .collect();
+            serde_json::to_string(&DevicesEvent {
+                devices: &devices.wifi.clientinfo,
+                changes,
+            })
+        };
+        state.publish("devices", event);
+        state.devices = Some(devices);
+    }
+
+    pub fn update_temperature(&self, cm_state: &CmState) {
+        let mut state = self.state.lock().unwrap();
+        let sample = TemperatureSample {
+            time: Utc::now(),
+            temperature: cm_state.temperature,
+            tuner_temperature: cm_state.tunner_temperature,
+        };
+        let event = serde_json::to_string(&TemperatureEvent {
+            sample: &sample,
+            state: cm_state,
+        });
+        state.publish("temperature", event);
+        state.temperatures.push_back(sample);
+        if state.temperatures.len() > MAX_TEMPERATURE_SAMPLES {
+            state.temperatures.pop_front();
+        }
+    }
+
+    pub fn update_status(
+        &self,
+        settings: GlobalSettings,
+        system: CmSystemInfo,
+        channels: DocsisChannels,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        let status = Status {
+            settings,
+            system,
+            channels,
+        };
+        state.publish("status", serde_json::to_string(&status));
+        state.status = Some(status);
+    }
+
+    // Opens a stream of events for a browser, starting with the current state.
+    fn subscribe(&self) -> mpsc::UnboundedReceiver<String> {
+        let mut state = self.state.lock().unwrap();
+        let (sender, receiver) = mpsc::unbounded();
+        let snapshot = serde_json::to_string(&Snapshot {
+            devices: state
+                .devices
+                .as_ref()
+                .map(|devices| &devices.wifi.clientinfo[..]),
+            status: state.status.as_ref(),
+            temperatures: &state.temperatures,
+        });
+        if let Ok(snapshot) = snapshot {
+            let _ = sender.unbounded_send(format_event("snapshot", &snapshot));
+        }
+        state.subscribers.push(sender);
+        receiver
+    }
+}
+
+impl State {
+    // Sends an event to the connected browsers, and forgets those that disconnected.
+    fn publish(&mut self, event: &str, data: serde_json::Result<String>) {
+        let data = match data {
+            Ok(data) => data,
+            Err(e) => {
+                debug!("Failed to serialize the {} event: {}", event, e);
+                return;
+            }
+        };
+        let message = format_event(event, &data);
+        self.subscribers
+            .retain(|subscriber| subscriber.unbounded_send(message.clone()).is_ok());
+    }
+}
+
+fn format_event(event: &str, data: &str) -> String {
+    format!("event: {}\ndata: {}\n\n", event, data)
+}
+
+/// Serves the dashboard and the API until an error occurs. Calls of the API are made on the shared
+/// router session.
+pub async fn serve(
+    config: &Config,
+    dashboard: Arc<Dashboard>,
+    router: SharedRouter,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let authorization = config
+        .basic_auth
+        .as_ref()
+        .map(|credentials| format!("Basic {}", base64::encode(credentials)));
+    let make_service = make_service_fn(move |_| {
+        let dashboard = dashboard.clone();
+        let router = router.clone();
+        let authorization = authorization.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let dashboard = dashboard.clone();
+                let router = router.clone();
+                let authorization = authorization.clone();
+                async move {
+                    let response =
+                        handle(&request, &dashboard, &router, authorization.as_deref()).await;
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&config.listen)?.serve(make_service);
+    info!("Serving the dashboard on http://{}", config.listen);
+    server.await?;
+    Ok(())
+}
+
+async fn handle(
+    request: &Request<Body>,
+    dashboard: &Dashboard,
+    router: &SharedRouter,
+    authorization: Option<&str>,
+) -> Response<Body> {
+    debug!("{} {}", request.method(), request.uri());
+    if let Err(message) = check_same_origin(request) {
+        debug!("Rejected request: {}", message);
+        return json_error(StatusCode::FORBIDDEN, message);
+    }
+    if let Some(expected) = authorization {
+        let given = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .map_or(&[][..], |value| value.as_bytes());
+        if !constant_time_eq(given, expected.as_bytes()) {
+            let mut response = status_response(StatusCode::UNAUTHORIZED);
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Basic realm=\"connect-box\""),
+            );
+            return response;
+        }
+    }
+
+    match (request.method(), request.uri().path()) {
+        (&Method::GET, "/") => {
+            let mut response = Response::new(Body::from(DASHBOARD_HTML));
+            response.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("text/html; charset=utf-8"),
+            );
+            response
+        }
+        (&Method::GET, "/events") => {
+            let events = dashboard.subscribe().map(Ok::<_, Infallible>);
+            let mut response = Response::new(Body::wrap_stream(events));
+            let headers = response.headers_mut();
+            headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("text/event-stream"),
+            );
+            headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+            response
+        }
+        (&Method::GET, "/devices") => api_response(router, Call::Devices).await,
+        (&Method::GET, "/status") => api_response(router, Call::Status).await,
+        (&Method::GET, "/channels") => api_response(router, Call::Channels).await,
+        (&Method::POST, "/reboot") => api_response(router, Call::Reboot).await,
+        (&Method::POST, path) => match block_path(path).map(str::parse::<MacAddr>) {
+            Some(Ok(mac)) => api_response(router, Call::Block(mac)).await,
+            Some(Err(_)) => json_error(StatusCode::BAD_REQUEST, "Invalid MAC address"),
+            None => status_response(StatusCode::NOT_FOUND),
+        },
+        _ => status_response(StatusCode::NOT_FOUND),
+    }
+}
+
+// Rejects the requests that another web site could make through the browser of the user:
+// - with a Host other than an IP address or localhost, which a page of another domain resolving to
+//   the address of connect-box would send (DNS rebinding),
+// - from another origin,
+// - POSTs that a plain HTML form can send, i.e. without a JSON content type or a custom header,
+//   since those are made without asking connect-box first (CORS preflight).
+fn check_same_origin(request: &Request<Body>) -> Result<(), &'static str> {
+    let headers = request.headers();
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .ok_or("Missing Host header")?;
+    if !is_local_host(host) {
+        return Err("The Host must be an IP address or localhost");
+    }
+
+    if let Some(origin) = headers.get(header::ORIGIN) {
+        let origin_host = origin
+            .to_str()
+            .ok()
+            .and_then(|origin| {
+                origin
+                    .strip_prefix("http://")
+                    .or_else(|| origin.strip_prefix("https://"))
+            })
+            .ok_or("Cross-origin requests are not allowed")?;
+        if !origin_host.eq_ignore_ascii_case(host) {
+            return Err("Cross-origin requests are not allowed");
+        }
+    }
+
+    if request.method() == Method::POST {
+        let json = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .and_then(|content_type| content_type.split(';').next())
+            .map_or(false, |mime| {
+                mime.trim().eq_ignore_ascii_case("application/json")
+            });
+        if !json && !headers.contains_key("x-requested-with") {
+            return Err(
+                "POST requests need a Content-Type of application/json or an X-Requested-With header",
+            );
+        }
+    }
+    Ok(())
+}
+
+// Whether the host of a Host header is an IP address or localhost, rather than a domain name.
+fn is_local_host(host: &str) -> bool {
+    let host = match host.parse::<Authority>() {
+        Ok(authority) => authority,
+        Err(_) => return false,
+    };
+    let name = host.host();
+    let name = name
+        .strip_prefix('[')
+        .and_then(|name| name.strip_suffix(']'))
+        .unwrap_or(name);
+    name.eq_ignore_ascii_case("localhost") || name.parse::<IpAddr>().is_ok()
+}
+
+// MAC address in a "/devices/{mac}/block" path.
+fn block_path(path: &str) -> Option<&str> {
+    path.strip_prefix("/devices/")?.strip_suffix("/block")
+}
+
+async fn api_response(router: &SharedRouter, call: Call) -> Response<Body> {
+    match api::call(router, call).await {
+        Ok(json) => {
+            let mut response = Response::new(Body::from(json));
+            response.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            );
+            response
+        }
+        Err(e) => json_error(StatusCode::BAD_GATEWAY, &e.to_string()),
+    }
+}
+
+fn json_error(status: StatusCode, message: &str) -> Response<Body> {
+    let mut response = Response::new(Body::from(
+        serde_json::json!({ "error": message }).to_string(),
+    ));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::from(status.canonical_reason().unwrap_or_default()));
+    *response.status_mut() = status;
+    response
+}
+
+// Compares credentials in a time that doesn't depend on where they differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo::DemoRouter;
+    use crate::session;
+    use futures::future;
+
+    fn get(path: &str) -> hyper::http::request::Builder {
+        Request::get(path).header(header::HOST, "127.0.0.1:8080")
+    }
+
+    fn post(path: &str) -> hyper::http::request::Builder {
+        Request::post(path)
+            .header(header::HOST, "127.0.0.1:8080")
+            .header(header::CONTENT_TYPE, "application/json")
+    }
+
+    // Handles requests one after another, with a session on the demo router.
+    async fn handle_all(
+        requests: Vec<hyper::http::request::Builder>,
+        authorization: Option<&str>,
+    ) -> Vec<(StatusCode, String)> {
+        let (shared, session) = session::shared();
+        let mut router = DemoRouter::new();
+        let dashboard = Dashboard::new();
+        let responses = async move {
+            let mut responses = Vec::new();
+            for request in requests {
+                let request = request.body(Body::empty()).unwrap();
+                let response = handle(&request, &dashboard, &shared, authorization).await;
+                let status = response.status();
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                responses.push((status, String::from_utf8(body.to_vec()).unwrap()));
+            }
+            responses
+        };
+        let ((), responses) = future::join(session.run(&mut router), responses).await;
+        responses
+    }
+
+    async fn handle_one(request: hyper::http::request::Builder) -> (StatusCode, String) {
+        handle_all(vec![request], None).await.remove(0)
+    }
+
+    #[tokio::test]
+    async fn devices() {
+        let (status, body) = handle_one(get("/devices")).await;
+        assert_eq!(status, StatusCode::OK);
+        let devices: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(devices.as_array().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn status_and_channels() {
+        let responses = handle_all(vec![get("/status"), get("/channels")], None).await;
+        assert_eq!(responses[0].0, StatusCode::OK);
+        let status: serde_json::Value = serde_json::from_str(&responses[0].1).unwrap();
+        assert_eq!(status["temperature"]["Temperature"], 34);
+        assert_eq!(responses[1].0, StatusCode::OK);
+        let channels: serde_json::Value = serde_json::from_str(&responses[1].1).unwrap();
+        assert_eq!(channels["downstream"].as_array().unwrap().len(), 8);
+    }
+
+    #[tokio::test]
+    async fn block_device() {
+        let responses = handle_all(
+            vec![post("/devices/AB:CD:EF:01:23:45/block"), get("/devices")],
+            None,
+        )
+        .await;
+        assert_eq!(responses[0].0, StatusCode::OK);
+        assert!(responses[0].1.contains("Blocked AB:CD:EF:01:23:45"));
+        assert!(!responses[1].1.contains("AB:CD:EF:01:23:45"));
+    }
+
+    #[tokio::test]
+    async fn invalid_mac_address() {
+        let (status, _) = handle_one(post("/devices/not-a-mac/block")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn reboot() {
+        let (status, body) = handle_one(post("/reboot")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("rebooting"));
+    }
+
+    #[tokio::test]
+    async fn unknown_routes() {
+        let responses = handle_all(
+            vec![get("/reboot"), post("/devices"), get("/nothing")],
+            None,
+        )
+        .await;
+        for (status, _) in responses {
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[tokio::test]
+    async fn form_posts_are_rejected() {
+        let form = Request::post("/reboot")
+            .header(header::HOST, "127.0.0.1:8080")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+        let plain = Request::post("/reboot").header(header::HOST, "127.0.0.1:8080");
+        let custom_header = Request::post("/reboot")
+            .header(header::HOST, "127.0.0.1:8080")
+            .header("X-Requested-With", "curl");
+        let responses = handle_all(vec![form, plain, custom_header], None).await;
+        assert_eq!(responses[0].0, StatusCode::FORBIDDEN);
+        assert_eq!(responses[1].0, StatusCode::FORBIDDEN);
+        assert_eq!(responses[2].0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn foreign_origins_are_rejected() {
+        let responses = handle_all(
+            vec![
+                post("/reboot").header(header::ORIGIN, "http://evil.example"),
+                get("/devices").header(header::ORIGIN, "null"),
+                get("/devices").header(header::ORIGIN, "http://127.0.0.1:8080"),
+            ],
+            None,
+        )
+        .await;
+        assert_eq!(responses[0].0, StatusCode::FORBIDDEN);
+        assert_eq!(responses[1].0, StatusCode::FORBIDDEN);
+        assert_eq!(responses[2].0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn domain_names_are_rejected() {
+        let host = |host| Request::get("/devices").header(header::HOST, host);
+        let responses = handle_all(
+            vec![
+                host("rebind.example:8080"),
+                Request::get("/devices"),
+                host("localhost:8080"),
+                host("[::1]:8080"),
+                host("192.168.0.10"),
+            ],
+            None,
+        )
+        .await;
+        let statuses: Vec<StatusCode> = responses.into_iter().map(|(status, _)| status).collect();
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::FORBIDDEN,
+                StatusCode::FORBIDDEN,
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::OK
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn basic_auth() {
+        let authorization = format!("Basic {}", base64::encode("admin:secret"));
+        let responses = handle_all(
+            vec![
+                get("/devices"),
+                get("/devices").header(header::AUTHORIZATION, "Basic YWRtaW46d3Jvbmc="),
+                get("/devices").header(header::AUTHORIZATION, authorization.as_str()),
+            ],
+            Some(&authorization),
+        )
+        .await;
+        assert_eq!(responses[0].0, StatusCode::UNAUTHORIZED);
+        assert_eq!(responses[1].0, StatusCode::UNAUTHORIZED);
+        assert_eq!(responses[2].0, StatusCode::OK);
+    }
+}